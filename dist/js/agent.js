@@ -14,7 +14,9 @@ async function toggleDaemon() {
         showToast("Agent daemon stopped", "info");
     } else {
         try {
-            await invokeShort("start_daemon");
+            // Resolves once the daemon answers health checks, which can take a few seconds. One
+            // already running (e.g. started from a terminal) is taken over instead of doubled.
+            await invokeLong("start_daemon", { adoptExisting: true }, 30000);
             daemonRunning = true;
             updateDaemonUI();
             var chatEl = document.getElementById("agent-chat-messages");
@@ -22,6 +24,13 @@ async function toggleDaemon() {
             showToast("Agent daemon started", "success");
             startTelegramPolling();
         } catch(e) {
+            // Started elsewhere (e.g. by the watchdog) since the UI last looked
+            if (e && e.code === "daemon_already_running") {
+                daemonRunning = true;
+                updateDaemonUI();
+                startTelegramPolling();
+                return;
+            }
             showToast("Failed to start daemon: " + (e.message || e), "error");
         }
     }
@@ -45,9 +54,21 @@ function updateDaemonUI() {
 }
 
 async function emergencyFlush() {
+    // Preview first: the flush can't be undone, and only runs with the token that comes with the plan
+    var request;
+    try {
+        request = await invokeShort("request_emergency_flush");
+        var plan = request.plan;
+        var summary = "About to kill " + plan.processes.length + " process(es) and delete " + plan.temp_files.length + " temp file(s).";
+        if (plan.processes.length) {
+            summary += "\n\n" + plan.processes.map(function(p) { return p.name + " (PID " + p.pid + ")"; }).join("\n");
+        }
+        if (!confirm(summary + "\n\nContinue?")) return;
+    } catch(e) {}
+
     showToast("Flushing system processes...", "info");
     stopTelegramPolling();
-    try { await invokeShort("emergency_flush"); } catch(e) {
+    try { await invokeShort("confirm_emergency_flush", { token: request ? request.token : "" }); } catch(e) {
         try { await invokeShort("stop_daemon"); } catch(e2) {}
     }
     daemonRunning = false;
@@ -171,7 +192,8 @@ async function executeTool(toolName, args) {
         } else if (toolName === "take_screenshot") {
             var fname = args.filename || "screenshot.png";
             var platform4 = await invokeShort("get_platform");
-            var screenshotPath = platform4 === "windows" ? "C:\\tmp\\" + fname : "/tmp/" + fname;
+            var tmpDir4 = await invokeShort("get_agent_temp_dir");
+            var screenshotPath = tmpDir4 + (platform4 === "windows" ? "\\" : "/") + fname;
             var pyCmd = "import pyautogui; pyautogui.screenshot('" + screenshotPath.replace(/\\/g,"\\\\") + "'); print('" + screenshotPath + "')";
             var result = await invokeShort("run_shell_command", { commandName: "python", args: ["-c", pyCmd] });
             return "Screenshot saved to: " + screenshotPath + "\n" + (result || "");
@@ -288,7 +310,8 @@ async function executeSkillTool(toolName, args) {
                 "    pg.wait_for_load_state('networkidle', timeout=10000)\n" +
                 "    print(pg.inner_text('body')[:8000])\n" +
                 "    b.close()\n";
-            var pyFile = platform === "windows" ? "C:\\tmp\\bc_browse.py" : "/tmp/bc_browse.py";
+            var tmpDir = await invokeShort("get_agent_temp_dir");
+            var pyFile = tmpDir + (platform === "windows" ? "\\" : "/") + "bc_browse.py";
             await invokeShort("run_shell_command", platform === "windows"
                 ? { commandName: "powershell", args: ["-Command", "Set-Content -Path '" + pyFile + "' -Value '" + pyScript.replace(/'/g, "''") + "'"] }
                 : { commandName: "bash", args: ["-c", "cat > '" + pyFile + "' << 'BCEOF'\n" + pyScript + "\nBCEOF"] });
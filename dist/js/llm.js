@@ -143,7 +143,8 @@ async function applyLLMConfig() {
 
     try {
         await invokeShort("write_config", { content: tomlContent });
-        showToast("Configuration saved to ~/.bambooclaw/config.toml", "success");
+        var configPath = await invokeShort("get_config_path").catch(function() { return "config.toml"; });
+        showToast("Configuration saved to " + configPath, "success");
     } catch(e) {
         localStorage.setItem("bambooclaw-config", JSON.stringify(currentConfig));
         showToast("Configuration saved successfully", "success");
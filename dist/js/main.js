@@ -3,6 +3,7 @@ window.enterDashboard = async function() {
     try {
         appendLog("boot-log", "[DASH] enterDashboard() called");
         localStorage.setItem("bambooclaw-installed", "true");
+        tauriInvoke("set_install_step", { step: "completed", done: true }).catch(function() {});
         document.getElementById("wizard-view").style.display = "none";
         var dashEl = document.getElementById("dashboard-view");
         dashEl.style.display = "block";
@@ -176,6 +177,7 @@ document.getElementById("btn-step2-install").addEventListener("click", async fun
                     else if (detectedOS === "macos") r = await invokeLong("run_shell_command", { commandName: "brew", args: ["install", "python3"] });
                     else r = await invokeLong("run_shell_command", { commandName: "sh", args: ["-c", "sudo apt install -y python3 python3-pip"] });
                     appendLog(log, r); appendLog(log, "[OK] Python installed.");
+                    tauriInvoke("clear_prerequisite_cache").catch(function() {});
                 } catch(e) { appendLog(log, "[ERROR] Python installation failed: " + (e.message || e)); }
             }
             appendLog(log, "[INFO] Installing Python packages...");
@@ -205,6 +207,7 @@ document.getElementById("btn-step2-install").addEventListener("click", async fun
                     else if (detectedOS === "macos") r5 = await invokeLong("run_shell_command", { commandName: "brew", args: ["install", "node"] });
                     else r5 = await invokeLong("run_shell_command", { commandName: "sh", args: ["-c", "sudo apt install -y nodejs npm"] });
                     appendLog(log, r5); appendLog(log, "[OK] Node.js installed.");
+                    tauriInvoke("clear_prerequisite_cache").catch(function() {});
                 } catch(e) { appendLog(log, "[ERROR] Node.js installation failed: " + (e.message || e)); }
             }
         }
@@ -333,6 +336,18 @@ async function boot() {
         fetchVersion();
         if (installedFlag === "true") {
             await window.enterDashboard();
+            // An app update doesn't replace the binary; point out a mismatch without blocking
+            invokeShort("check_bambooclaw_version").then(function(check) {
+                if (check.warning) showToast(check.warning, "error");
+            }).catch(function() {});
+            // A daemon left running by a previous session counts as started
+            try {
+                if (await invokeShort("adopt_existing_daemon")) {
+                    daemonRunning = true;
+                    updateDaemonUI();
+                    startTelegramPolling();
+                }
+            } catch(e) {}
             loadPersonas();
             if (!currentConfig.settings) {
                 currentConfig.settings = { autonomy: "autonomous", identity: "bamboo", loglevel: "info", maxToolIterations: 10 };
@@ -360,7 +375,7 @@ async function boot() {
             setTimeout(function() { renderActiveToolsSummary(); renderEnabledIntegrations(); }, 2000);
             setTimeout(function() { if (currentConfig.llm && currentConfig.llm.api_key && !daemonRunning) toggleDaemon(); }, 500);
         } else {
-            wizardGo(0);
+            await resumeWizard();
         }
     } catch(e) {}
 }
@@ -13,6 +13,29 @@ function wizardGo(step) {
     if (step === 1) runPrereqInstall();
 }
 
+// Reopen the wizard where an earlier session left it. Steps past the system checks need
+// detectedOS, which the checks would otherwise have filled in. A downloaded binary that doesn't
+// match this app's version is downloaded again.
+async function resumeWizard() {
+    var state = {};
+    try { state = await invokeShort("get_install_state"); } catch(e) {}
+    var step = state.binary_downloaded ? 3 : state.prereqs_installed ? 2 : 0;
+    if (step === 3) {
+        try {
+            var check = await invokeShort("check_bambooclaw_version");
+            if (check.warning) {
+                appendLog("boot-log", "[WARN] " + check.warning + ".");
+                step = 1;
+            }
+        } catch(e) {}
+    }
+    if (step > 0) {
+        try { detectedOS = await invokeShort("get_platform"); } catch(e) {}
+        appendLog("boot-log", "[INFO] Resuming setup where it was left off.");
+    }
+    wizardGo(step);
+}
+
 function updateBadge(id, text, cls) {
     var el = document.getElementById(id);
     if (el) { el.textContent = text; el.className = "status-badge " + cls; }
@@ -30,41 +53,23 @@ async function runSystemChecks() {
         updateBadge("chk-os", "Windows (assumed)", "status-not-installed");
     });
 
-    var checkRust = invokeShort("check_prerequisite", { name: "rustc" }).then(function(rv) {
-        updateBadge("chk-rust", rv.trim().split("\n")[0], "status-found");
-    }).catch(function() {
-        updateBadge("chk-rust", "Not Installed", "status-not-installed");
-    });
-
-    var checkGit = invokeShort("run_shell_command", { commandName: "git", args: ["--version"] }).then(function(gv) {
-        updateBadge("chk-git", gv.trim().split("\n")[0], "status-found");
-    }).catch(function() {
-        updateBadge("chk-git", "Not Installed", "status-not-installed");
-    });
-
-    var checkBuild = invokeShort("check_prerequisite", { name: "vs_build_tools" }).then(function(bv) {
-        updateBadge("chk-build", bv.trim() || "Found", "status-found");
-    }).catch(function() {
-        updateBadge("chk-build", detectedOS === "windows" ? "Not Installed" : "N/A", detectedOS === "windows" ? "status-not-installed" : "status-found");
-    });
-
-    var checkPython = invokeShort("run_shell_command", { commandName: "python", args: ["--version"] }).then(function(pv) {
-        updateBadge("chk-python", pv.trim().split("\n")[0], "status-found");
-    }).catch(function() {
-        return invokeShort("run_shell_command", { commandName: "python3", args: ["--version"] }).then(function(pv) {
-            updateBadge("chk-python", pv.trim().split("\n")[0], "status-found");
-        }).catch(function() {
-            updateBadge("chk-python", "Not Installed", "status-not-installed");
+    // All tool checks run concurrently in the backend, in a single call
+    var checkTools = invokeShort("check_all_prerequisites", { names: ["rustc", "git", "vs_build_tools", "python", "node"] }).then(function(results) {
+        [["chk-rust", "rustc"], ["chk-git", "git"], ["chk-python", "python"], ["chk-node", "node"]].forEach(function(pair) {
+            var r = results[pair[1]];
+            if (r && r.ok) updateBadge(pair[0], r.detail.split("\n")[0], "status-found");
+            else updateBadge(pair[0], "Not Installed", "status-not-installed");
         });
-    });
-
-    var checkNode = invokeShort("run_shell_command", { commandName: "node", args: ["--version"] }).then(function(nv) {
-        updateBadge("chk-node", nv.trim().split("\n")[0], "status-found");
+        var bv = results.vs_build_tools;
+        if (bv && bv.ok) updateBadge("chk-build", bv.detail || "Found", "status-found");
+        else updateBadge("chk-build", detectedOS === "windows" ? "Not Installed" : "N/A", detectedOS === "windows" ? "status-not-installed" : "status-found");
     }).catch(function() {
-        updateBadge("chk-node", "Not Installed", "status-not-installed");
+        ["chk-rust", "chk-git", "chk-build", "chk-python", "chk-node"].forEach(function(id) {
+            updateBadge(id, "Not Installed", "status-not-installed");
+        });
     });
 
-    await Promise.allSettled([checkOS, checkRust, checkGit, checkBuild, checkPython, checkNode]);
+    await Promise.allSettled([checkOS, checkTools]);
     document.getElementById("btn-step0-next").disabled = false;
 }
 
@@ -90,6 +95,8 @@ async function runPrereqInstall() {
             }
             appendLog(log, result);
             appendLog(log, "[OK] Rust installation completed.");
+            // Cached prerequisite results are stale now
+            tauriInvoke("clear_prerequisite_cache").catch(function() {});
         } catch(e) {
             appendLog(log, "[ERROR] Rust installation failed: " + (e.message || e));
         }
@@ -107,6 +114,7 @@ async function runPrereqInstall() {
             });
             appendLog(log, result);
             appendLog(log, "[OK] Build Tools installation completed.");
+            tauriInvoke("clear_prerequisite_cache").catch(function() {});
         } catch(e) {
             appendLog(log, "[ERROR] Build Tools installation failed: " + (e.message || e));
         }
@@ -121,43 +129,41 @@ async function runPrereqInstall() {
     }
 
     appendLog(log, "[DONE] All prerequisites ready.");
+    tauriInvoke("set_install_step", { step: "prereqs_installed", done: true }).catch(function() {});
     document.getElementById("btn-step1-next").disabled = false;
 }
 
 async function deployBinary(log) {
     appendLog(log, "[INFO] Deploying pre-built binary...");
     try {
-        var metaRes = await fetch(PROXY_URL);
-        if (!metaRes.ok) throw new Error("Proxy returned " + metaRes.status);
-        var meta = await metaRes.json();
-
-        var assets = meta.assets || [];
-        var asset;
-        if (detectedOS === "windows") {
-            asset = assets.find(function(a) { return a.name.endsWith(".exe"); }) || assets.find(function(a) { return a.name.endsWith(".msi"); });
-        } else if (detectedOS === "macos") {
-            asset = assets.find(function(a) { return a.name.endsWith(".dmg"); }) || assets.find(function(a) { return a.name.includes("macos"); });
-        } else {
-            asset = assets.find(function(a) { return a.name.endsWith(".AppImage"); }) || assets.find(function(a) { return a.name.includes("linux"); });
+        var net = await invokeShort("check_network", {});
+        if (!net.reachable) {
+            var hint = net.error === "dns" ? "Could not resolve the release server. Check your internet connection or DNS settings."
+                : net.error === "connection_refused" ? "The release server refused the connection. A firewall or proxy may be blocking it."
+                : net.error === "timeout" ? "The release server did not answer in time. Your connection may be slow or offline."
+                : "Could not reach the release server.";
+            appendLog(log, "[ERROR] " + hint + " (" + net.detail + ")");
+            return;
         }
-        var assetName = asset ? asset.name : "";
 
-        if (!assetName) {
-            appendLog(log, "[ERROR] Could not find a pre-built binary for your platform.");
+        var dlUrl;
+        try {
+            dlUrl = await invokeLong("resolve_binary_url", { version: "latest" }, 20000);
+        } catch(e) {
+            appendLog(log, "[ERROR] Could not find a pre-built binary for your platform: " + (e.message || e));
             return;
         }
 
-        var homePath = "~";
+        // Honors BAMBOOCLAW_HOME and XDG, so it may not be ~/.bambooclaw
+        var destDir;
         try {
-            if (detectedOS === "windows") {
-                homePath = (await invokeShort("run_shell_command", { commandName: "cmd", args: ["/C", "echo %USERPROFILE%"] })).trim();
-            } else {
-                homePath = (await invokeShort("run_shell_command", { commandName: "sh", args: ["-c", "echo $HOME"] })).trim();
-            }
-        } catch(e) {}
+            destDir = await invokeShort("get_bambooclaw_config_dir");
+        } catch(e) {
+            appendLog(log, "[ERROR] Could not find the BambooClaw folder: " + (e.message || e));
+            return;
+        }
 
         var sep = detectedOS === "windows" ? "\\" : "/";
-        var destDir = homePath + sep + ".bambooclaw";
         var destFile = destDir + sep + (detectedOS === "windows" ? "bambooclaw.exe" : "bambooclaw");
 
         try {
@@ -168,12 +174,37 @@ async function deployBinary(log) {
             }
         } catch(e) {}
 
-        var dlUrl = PROXY_URL + "?asset=" + encodeURIComponent(assetName);
-        await invokeLong("download_binary", { url: dlUrl, dest: destFile });
+        // Fail early with a clear message rather than a write error halfway through the download.
+        // Room for the binary twice over (partial file + final); 200 MB covers every release so far.
+        var requiredBytes = 200 * 1024 * 1024;
+        try {
+            await invokeShort("check_disk_space", { path: destDir, requiredBytes: requiredBytes });
+        } catch(e) {
+            appendLog(log, "[ERROR] " + (e.message || e));
+            return;
+        }
+
+        // executable: rejects an HTML error page and sets the exec bit on macOS/Linux
+        await invokeLong("download_binary", { url: dlUrl, dest: destFile, executable: true });
         appendLog(log, "[OK] Binary downloaded successfully.");
 
-        if (detectedOS !== "windows") {
-            try { await invokeShort("run_shell_command", { commandName: "chmod", args: ["+x", destFile] }); } catch(e) {}
+        // A complete download can still be for the wrong architecture; running it is the only proof
+        try {
+            var version = await invokeLong("validate_binary", { path: destFile }, 20000);
+            appendLog(log, "[OK] Verified bambooclaw " + version + " runs on this system.");
+        } catch(e) {
+            appendLog(log, "[ERROR] " + (e.message || e));
+            return;
+        }
+
+        try {
+            var pathUpdate = await invokeLong("add_to_path", {}, 30000);
+            if (pathUpdate.added) {
+                appendLog(log, "[OK] Added " + destDir + " to PATH (" + pathUpdate.location + ").");
+                if (pathUpdate.restart_required) appendLog(log, "[INFO] Restart any open terminals to use the bambooclaw command.");
+            }
+        } catch(e) {
+            appendLog(log, "[WARN] Could not add BambooClaw to PATH: " + (e.message || e));
         }
 
         document.getElementById("install-path-display").value = destFile;
@@ -186,6 +217,8 @@ async function deployBinary(log) {
 // =========== CONFIG SERIALIZATION ===========
 function buildConfigToml() {
     var lines = ["# BambooClaw Agent Configuration", "# Auto-generated by BambooClaw Companion", ""];
+    // Must match CURRENT_SCHEMA_VERSION in src-tauri/src/config/migrate.rs
+    lines.push("schema_version = 1", "");
 
     if (currentConfig.llm) {
         lines.push("[llm]");
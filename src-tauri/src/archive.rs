@@ -0,0 +1,570 @@
+use crate::error::AppError;
+use crate::progress::{emit_progress, ProgressEvent};
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+// Message of the Cancelled error returned when an extraction is stopped via cancel_extract
+const EXTRACT_CANCELLED: &str = "Extraction cancelled";
+
+// How often progress is sent while a single large entry is being written
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+// An `extract` progress event, sent after each archive entry is written and, while a
+// large entry is being written, at most every PROGRESS_INTERVAL. `files_total` is only known for
+// zip archives; tar entries are read one after another. The byte counts are of the archive file
+// itself, so a compressed archive also ends at `bytes_total`.
+#[derive(Clone, Serialize)]
+pub(crate) struct ExtractProgress {
+    archive: String,
+    entry: String,
+    files_done: usize,
+    files_total: Option<usize>,
+    bytes_done: u64,
+    bytes_total: u64,
+}
+
+#[derive(Clone, Copy)]
+enum ArchiveFormat {
+    Zip,
+    TarGz,
+    Tar,
+}
+
+// Cancellation tokens for running extractions, keyed by the archive path they were started with
+#[derive(Default)]
+pub struct ExtractState(Mutex<HashMap<String, CancellationToken>>);
+
+// 31. Extract a .zip, .tar.gz/.tgz or .tar archive into `dest_dir` and return the paths of the
+// files written. The format is recognised from the file's first bytes, falling back to its
+// extension. Unix permissions stored in the archive are kept. An entry whose path, or whose link
+// target, would land outside `dest_dir` fails the whole extraction.
+// Entries are streamed from disk one at a time, so archives larger than memory are fine.
+// Progress is reported through `extract` progress events. An extraction can be aborted with
+// cancel_extract, which removes what it had written and fails it with EXTRACT_CANCELLED.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(app, state), err)]
+pub async fn extract_archive(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ExtractState>,
+    archive_path: String,
+    dest_dir: String,
+) -> Result<Vec<String>, AppError> {
+    let cancel = CancellationToken::new();
+    {
+        let mut extractions = state.0.lock().unwrap();
+        if extractions.contains_key(&archive_path) {
+            return Err(AppError::InvalidInput(format!(
+                "An extraction of '{}' is already in progress",
+                archive_path
+            )));
+        }
+        extractions.insert(archive_path.clone(), cancel.clone());
+    }
+
+    let archive = archive_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        extract(&app, Path::new(&archive), Path::new(&dest_dir), cancel)
+    })
+    .await
+    .map_err(|e| AppError::Other(format!("Task join error: {}", e)));
+
+    state.0.lock().unwrap().remove(&archive_path);
+    result?
+}
+
+// 31b. Abort an in-flight extract_archive call; the files it had written are removed
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(state), err)]
+pub fn cancel_extract(
+    state: tauri::State<ExtractState>,
+    archive_path: String,
+) -> Result<String, AppError> {
+    match state.0.lock().unwrap().get(&archive_path) {
+        Some(token) => {
+            token.cancel();
+            Ok("Extraction cancellation requested".to_string())
+        }
+        None => Err(AppError::NotFound(format!(
+            "No extraction in progress for '{}'",
+            archive_path
+        ))),
+    }
+}
+
+fn extract(
+    app: &tauri::AppHandle,
+    archive: &Path,
+    dest: &Path,
+    cancel: CancellationToken,
+) -> Result<Vec<String>, AppError> {
+    let format = archive_format(archive)?;
+    let dest_existed = dest.exists();
+    std::fs::create_dir_all(dest)
+        .map_err(|e| AppError::io(format!("Failed to create {}", dest.display()), e))?;
+    let file = File::open(archive)
+        .map_err(|e| AppError::io(format!("Failed to open {}", archive.display()), e))?;
+    let bytes_total = file
+        .metadata()
+        .map_err(|e| AppError::io(format!("Failed to read {}", archive.display()), e))?
+        .len();
+
+    let tracker = Tracker::new(app, archive, bytes_total, cancel);
+    let reader = TrackedReader {
+        inner: file,
+        tracker: &tracker,
+    };
+    let result = match format {
+        ArchiveFormat::Zip => extract_zip(reader, dest, &tracker),
+        ArchiveFormat::TarGz => extract_tar(GzDecoder::new(reader), dest, &tracker),
+        ArchiveFormat::Tar => extract_tar(reader, dest, &tracker),
+    };
+    // A cancel surfaces as a read error from whichever entry was being written
+    if result.is_err() && tracker.cancel.is_cancelled() {
+        tracker.remove_written(dest, dest_existed);
+        return Err(AppError::Cancelled(EXTRACT_CANCELLED.to_string()));
+    }
+    result.map_err(|e| e.map_message(|message| format!("{}: {}", archive.display(), message)))
+}
+
+// Progress of one extraction, shared by the loop writing entries and the reader feeding it.
+// Everything runs on the one blocking thread, hence Cell rather than locks.
+struct Tracker<'a> {
+    app: &'a tauri::AppHandle,
+    archive: String,
+    cancel: CancellationToken,
+    entry: RefCell<PathBuf>,
+    files_done: Cell<usize>,
+    files_total: Cell<Option<usize>>,
+    position: Cell<u64>,
+    bytes_total: u64,
+    last_emit: Cell<Instant>,
+    // Every path an entry was written to, in order, for cleaning up after a cancel
+    written: RefCell<Vec<PathBuf>>,
+}
+
+impl<'a> Tracker<'a> {
+    fn new(
+        app: &'a tauri::AppHandle,
+        archive: &Path,
+        bytes_total: u64,
+        cancel: CancellationToken,
+    ) -> Self {
+        Tracker {
+            app,
+            archive: archive.display().to_string(),
+            cancel,
+            entry: RefCell::new(PathBuf::new()),
+            files_done: Cell::new(0),
+            files_total: Cell::new(None),
+            position: Cell::new(0),
+            bytes_total,
+            last_emit: Cell::new(Instant::now()),
+            written: RefCell::new(Vec::new()),
+        }
+    }
+
+    // Called before anything of the entry is written to `out`
+    fn start_entry(&self, relative: &Path, out: &Path) {
+        *self.entry.borrow_mut() = relative.to_path_buf();
+        if !relative.as_os_str().is_empty() {
+            self.written.borrow_mut().push(out.to_path_buf());
+        }
+    }
+
+    fn finish_entry(&self) {
+        self.files_done.set(self.files_done.get() + 1);
+        self.emit();
+    }
+
+    fn moved_to(&self, position: u64) {
+        self.position.set(position);
+        if self.last_emit.get().elapsed() >= PROGRESS_INTERVAL {
+            self.emit();
+        }
+    }
+
+    fn emit(&self) {
+        self.last_emit.set(Instant::now());
+        emit_progress(
+            self.app,
+            ProgressEvent::Extract(ExtractProgress {
+                archive: self.archive.clone(),
+                entry: self.entry.borrow().display().to_string(),
+                files_done: self.files_done.get(),
+                files_total: self.files_total.get(),
+                bytes_done: self.position.get().min(self.bytes_total),
+                bytes_total: self.bytes_total,
+            }),
+        );
+    }
+
+    // Removes what the extraction wrote, newest first, then the directories that leaves empty up
+    // to `dest`, and `dest` itself when the extraction created it. Directories that already held
+    // other files are kept.
+    fn remove_written(&self, dest: &Path, dest_existed: bool) {
+        for path in self.written.borrow().iter().rev() {
+            match std::fs::symlink_metadata(path) {
+                Ok(meta) if meta.is_dir() => {
+                    let _ = std::fs::remove_dir(path);
+                }
+                Ok(_) => {
+                    let _ = std::fs::remove_file(path);
+                }
+                Err(_) => {}
+            }
+            for parent in path.ancestors().skip(1) {
+                if parent == dest
+                    || !parent.starts_with(dest)
+                    || std::fs::remove_dir(parent).is_err()
+                {
+                    break;
+                }
+            }
+        }
+        if !dest_existed {
+            let _ = std::fs::remove_dir(dest);
+        }
+    }
+}
+
+// The archive file, reporting how far into it extraction has got and failing every read once
+// the extraction is cancelled, which stops even a single large entry part way
+struct TrackedReader<'a> {
+    inner: File,
+    tracker: &'a Tracker<'a>,
+}
+
+impl Read for TrackedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.tracker.cancel.is_cancelled() {
+            return Err(std::io::Error::other(EXTRACT_CANCELLED));
+        }
+        let read = self.inner.read(buf)?;
+        self.tracker
+            .moved_to(self.tracker.position.get() + read as u64);
+        Ok(read)
+    }
+}
+
+// Zip archives are read by seeking to each entry
+impl Seek for TrackedReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let position = self.inner.seek(pos)?;
+        self.tracker.position.set(position);
+        Ok(position)
+    }
+}
+
+// Magic bytes first, so a misnamed download still extracts; the extension only decides when
+// the content is unrecognised
+fn archive_format(path: &Path) -> Result<ArchiveFormat, AppError> {
+    let mut header = Vec::with_capacity(512);
+    File::open(path)
+        .and_then(|file| file.take(512).read_to_end(&mut header))
+        .map_err(|e| AppError::io(format!("Failed to read {}", path.display()), e))?;
+
+    if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        return Ok(ArchiveFormat::Zip);
+    }
+    if header.starts_with(&[0x1f, 0x8b]) {
+        return Ok(ArchiveFormat::TarGz);
+    }
+    if header.get(257..262) == Some(b"ustar") {
+        return Ok(ArchiveFormat::Tar);
+    }
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if name.ends_with(".zip") {
+        Ok(ArchiveFormat::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Ok(ArchiveFormat::TarGz)
+    } else if name.ends_with(".tar") {
+        Ok(ArchiveFormat::Tar)
+    } else {
+        Err(AppError::InvalidInput(format!(
+            "{} is not a zip, tar.gz or tar archive",
+            path.display()
+        )))
+    }
+}
+
+fn extract_zip(
+    reader: TrackedReader,
+    dest: &Path,
+    tracker: &Tracker,
+) -> Result<Vec<String>, AppError> {
+    let mut zip = zip::ZipArchive::new(reader).map_err(zip_error)?;
+    let root = canonical_dest(dest)?;
+    let total = zip.len();
+    tracker.files_total.set(Some(total));
+    let mut written = Vec::new();
+    for index in 0..total {
+        let mut entry = zip.by_index(index).map_err(zip_error)?;
+        let relative = entry
+            .enclosed_name()
+            .and_then(|name| contained_path(&name))
+            .ok_or_else(|| escaping_entry(entry.name()))?;
+        // An earlier symlink entry may have redirected a directory on the way
+        let parent = real_parent(&root, &relative).ok_or_else(|| escaping_entry(entry.name()))?;
+        let out = dest.join(&relative);
+        tracker.start_entry(&relative, &out);
+
+        if entry.is_dir() {
+            create_dir(&out)?;
+        } else if entry.is_symlink() {
+            let mut target = String::new();
+            entry
+                .read_to_string(&mut target)
+                .map_err(|e| AppError::io(format!("Failed to read entry '{}'", entry.name()), e))?;
+            check_link_target(&relative, &parent, Path::new(&target))?;
+            remove_existing(&out);
+            create_symlink(Path::new(&target), &out)?;
+        } else {
+            if let Some(parent) = out.parent() {
+                create_dir(parent)?;
+            }
+            remove_existing(&out);
+            let mut output = File::create(&out)
+                .map_err(|e| AppError::io(format!("Failed to create {}", out.display()), e))?;
+            std::io::copy(&mut entry, &mut output)
+                .map_err(|e| AppError::io(format!("Failed to write {}", out.display()), e))?;
+            #[cfg(unix)]
+            if let Some(mode) = entry.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&out, std::fs::Permissions::from_mode(mode & 0o777))
+                    .map_err(|e| {
+                        AppError::io(format!("Failed to set permissions of {}", out.display()), e)
+                    })?;
+            }
+            written.push(out.display().to_string());
+        }
+        tracker.finish_entry();
+    }
+    Ok(written)
+}
+
+fn extract_tar(reader: impl Read, dest: &Path, tracker: &Tracker) -> Result<Vec<String>, AppError> {
+    let mut tar = tar::Archive::new(reader);
+    let root = canonical_dest(dest)?;
+    let entries = tar
+        .entries()
+        .map_err(|e| AppError::io("Failed to read the archive", e))?;
+    let mut written = Vec::new();
+    for entry in entries {
+        let mut entry = entry.map_err(|e| AppError::io("Failed to read the archive", e))?;
+        let path = entry
+            .path()
+            .map_err(|e| AppError::io("Failed to read an entry name", e))?
+            .into_owned();
+        let relative =
+            contained_path(&path).ok_or_else(|| escaping_entry(&path.display().to_string()))?;
+        let kind = entry.header().entry_type();
+        if kind.is_symlink() || kind.is_hard_link() {
+            let target = entry
+                .link_name()
+                .map_err(|e| AppError::io("Failed to read a link target", e))?
+                .unwrap_or_default()
+                .into_owned();
+            if kind.is_symlink() {
+                let parent = real_parent(&root, &relative)
+                    .ok_or_else(|| escaping_entry(&path.display().to_string()))?;
+                check_link_target(&relative, &parent, &target)?;
+            } else if contained_path(&target).is_none() {
+                return Err(escaping_entry(&path.display().to_string()));
+            }
+        }
+
+        tracker.start_entry(&relative, &dest.join(&relative));
+        // unpack_in also refuses to write through a symlink that leads outside `dest`
+        entry
+            .unpack_in(dest)
+            .map_err(|e| AppError::io(format!("Failed to extract '{}'", relative.display()), e))?;
+        if kind.is_file() {
+            written.push(dest.join(&relative).display().to_string());
+        }
+        tracker.finish_entry();
+    }
+    Ok(written)
+}
+
+// `path` with `.` parts dropped, or None if it is absolute or climbs out with `..`. An entry
+// for the archive's top directory (`./`) comes back empty.
+fn contained_path(path: &Path) -> Option<PathBuf> {
+    let mut contained = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => contained.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !contained.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(contained)
+}
+
+fn canonical_dest(dest: &Path) -> Result<PathBuf, AppError> {
+    dest.canonicalize()
+        .map_err(|e| AppError::io(format!("Failed to resolve {}", dest.display()), e))
+}
+
+// Where the directory holding `relative` really is, relative to `root` (the canonical
+// destination), with symlinks that earlier entries created along the way followed. None if it
+// lies outside `root`. Parts that don't exist yet are taken as they are: the extraction creates
+// them as plain directories.
+fn real_parent(root: &Path, relative: &Path) -> Option<PathBuf> {
+    let mut existing = root.join(relative.parent().unwrap_or(Path::new("")));
+    let mut missing = Vec::new();
+    while std::fs::symlink_metadata(&existing).is_err() {
+        missing.push(existing.file_name()?.to_owned());
+        existing.pop();
+    }
+    // A dangling link fails here too
+    let resolved = existing.canonicalize().ok()?;
+    let inside = resolved.strip_prefix(root).ok()?.to_path_buf();
+    Some(
+        missing
+            .into_iter()
+            .rev()
+            .fold(inside, |path, part| path.join(part)),
+    )
+}
+
+// A symlink at `entry` may only point at something else inside the destination. `parent` is
+// where the link really lands, from real_parent, so a link can't be planted through another
+// link's directory with a target that only looks contained.
+fn check_link_target(entry: &Path, parent: &Path, target: &Path) -> Result<(), AppError> {
+    match contained_path(&parent.join(target)) {
+        Some(_) => Ok(()),
+        None => Err(AppError::InvalidInput(format!(
+            "Archive entry '{}' links outside the destination directory",
+            entry.display()
+        ))),
+    }
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> Result<(), AppError> {
+    if let Some(parent) = link.parent() {
+        create_dir(parent)?;
+    }
+    std::os::unix::fs::symlink(target, link)
+        .map_err(|e| AppError::io(format!("Failed to create {}", link.display()), e))
+}
+
+// Creating symlinks needs extra privileges on Windows; release archives for it don't use them
+#[cfg(not(unix))]
+fn create_symlink(_target: &Path, _link: &Path) -> Result<(), AppError> {
+    Ok(())
+}
+
+// Clears a file or symlink left by an earlier extraction, so nothing is written through a link
+fn remove_existing(path: &Path) {
+    if std::fs::symlink_metadata(path).is_ok_and(|m| !m.is_dir()) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn create_dir(dir: &Path) -> Result<(), AppError> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| AppError::io(format!("Failed to create {}", dir.display()), e))
+}
+
+fn escaping_entry(name: &str) -> AppError {
+    AppError::InvalidInput(format!(
+        "Archive entry '{}' points outside the destination directory",
+        name
+    ))
+}
+
+fn zip_error(e: zip::result::ZipError) -> AppError {
+    match e {
+        zip::result::ZipError::Io(e) => AppError::io("Failed to read the archive", e),
+        e => AppError::InvalidInput(format!("Not a valid zip archive: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh, canonical directory per test
+    fn temp_dest(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bambooclaw-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn contained_path_refuses_zip_slip() {
+        assert_eq!(contained_path(Path::new("../evil")), None);
+        assert_eq!(contained_path(Path::new("bin/../../evil")), None);
+        assert_eq!(
+            contained_path(Path::new("bin/../lib/./a.so")),
+            Some(PathBuf::from("lib/a.so"))
+        );
+    }
+
+    #[test]
+    fn contained_path_refuses_absolute_paths() {
+        assert_eq!(contained_path(Path::new("/etc/passwd")), None);
+        #[cfg(windows)]
+        assert_eq!(contained_path(Path::new("C:\\Windows\\evil.dll")), None);
+    }
+
+    #[test]
+    fn link_target_outside_destination_is_refused() {
+        let entry = Path::new("bin/link");
+        assert!(check_link_target(entry, Path::new("bin"), Path::new("../lib/a.so")).is_ok());
+        assert!(check_link_target(entry, Path::new("bin"), Path::new("../../etc")).is_err());
+        assert!(check_link_target(entry, Path::new("bin"), Path::new("/etc")).is_err());
+    }
+
+    // `a -> .` then `a/b -> ..`: textually `a/..` stays inside, but `a` is the destination
+    // itself, so `b` would point at its parent
+    #[cfg(unix)]
+    #[test]
+    fn chained_symlink_escape_is_refused() {
+        let root = temp_dest("chained-link");
+        std::os::unix::fs::symlink(".", root.join("a")).unwrap();
+
+        let entry = Path::new("a/b");
+        let parent = real_parent(&root, entry).unwrap();
+        assert_eq!(parent, PathBuf::new());
+        assert!(check_link_target(entry, &parent, Path::new("..")).is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    // A file entry under a directory an earlier entry made a link to the outside
+    #[cfg(unix)]
+    #[test]
+    fn write_through_escaping_symlink_is_refused() {
+        let root = temp_dest("escaping-link");
+        let outside = temp_dest("escaping-link-outside");
+        std::os::unix::fs::symlink(&outside, root.join("b")).unwrap();
+
+        assert_eq!(real_parent(&root, Path::new("b/x")), None);
+        assert_eq!(real_parent(&root, Path::new("b/new/x")), None);
+        assert_eq!(
+            real_parent(&root, Path::new("c/new/x")),
+            Some(PathBuf::from("c/new"))
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+        std::fs::remove_dir_all(&outside).unwrap();
+    }
+}
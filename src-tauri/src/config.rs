@@ -0,0 +1,1041 @@
+mod migrate;
+mod schema;
+
+use crate::bambooclaw_dir;
+use crate::daemon::{running_profile, DaemonState};
+use crate::error::AppError;
+use crate::wizard::complete_step;
+use schema::{ValidationWarning, WarningKind};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use toml_edit::{Array, DocumentMut, ImDocument, InlineTable, Item, Table, Value};
+
+// How many config backups are kept in each profile's backups dir
+const MAX_CONFIG_BACKUPS: usize = 10;
+
+// Stands in for secrets in redacted_config
+const REDACTED: &str = "***";
+
+// A key counts as secret when its name contains one of these; `[diagnostics] redact_keys` adds
+// more
+const SECRET_KEY_WORDS: &[&str] = &["key", "token", "secret", "password"];
+
+// Written by generate_default_config and whenever read_config finds no config.toml
+const DEFAULT_CONFIG: &str = r#"# BambooClaw configuration
+#
+# Generated by BambooClaw Companion. Every key is optional; removing one falls back to the
+# default shown here. Changes take effect the next time the daemon starts.
+
+# Layout version of this file, used to upgrade it automatically. Don't edit.
+schema_version = 1
+
+[llm]
+# LLM provider the agent talks to: "openrouter", "anthropic", "openai", "ollama", ...
+provider = "openrouter"
+# Model ID, in the naming scheme of the provider above
+model = "anthropic/claude-sonnet-4-6"
+# API key for the provider. Leave empty for local providers such as Ollama.
+api_key = ""
+# Base URL of a local provider (Ollama, LM Studio); ignored by hosted providers
+# local_url = "http://localhost:11434"
+
+[agent]
+# What the agent may do without asking first:
+#   "observe"       - read-only, never runs commands
+#   "collaborative" - asks for approval before risky actions
+#   "autonomous"    - acts without asking
+autonomy = "collaborative"
+# Daemon log verbosity: "error", "warn", "info", "debug" or "trace"
+log_level = "info"
+# Where the agent keeps temporary files (scripts, screenshots). The emergency flush clears it.
+# Defaults to a "bambooclaw" folder in the system temp directory.
+# temp_dir = "/tmp/bambooclaw"
+
+[gateway]
+# Address the daemon's HTTP gateway binds to. Keep 127.0.0.1 unless other machines need access.
+host = "127.0.0.1"
+# Port of the gateway; the app also uses it for daemon health checks
+port = 3000
+
+[shell]
+# Programs the app may launch on the agent's behalf, matched by exact name. Anything else is
+# refused. Unset means no restriction.
+# allowed_commands = ["git", "cargo", "python"]
+
+[daemon]
+# Arguments passed to `bambooclaw daemon` when the app starts it without any of its own
+# args = ["--port", "3000", "--verbose"]
+# How long a newly started daemon may take to answer health checks, and how often it is asked
+# ready_timeout_secs = 20
+# ready_poll_interval_ms = 250
+# Stop the daemon when the app is closed. By default it keeps running in the background and the
+# app picks it up again the next time it starts.
+# stop_daemon_on_exit = false
+
+[emergency]
+# The emergency flush stops the daemon and every process it started. When the app doesn't know
+# of a running daemon, it kills these programs instead, but only instances whose command line
+# refers to the agent's temp_dir or ~/.bambooclaw.
+# process_names = ["python"]
+# What to delete from the agent's temp_dir, as glob patterns relative to it
+# temp_patterns = ["*"]
+
+[downloads]
+# Folders downloads may be saved to besides the bambooclaw folder, as absolute paths
+# allowed_dirs = ["/opt/models"]
+# PEM file of extra root certificates to trust, for networks that intercept TLS with their own CA.
+# Defaults to the SSL_CERT_FILE environment variable.
+# ca_cert_file = "/etc/ssl/certs/corporate-ca.pem"
+# DANGER: turns off TLS certificate checks for downloads and update checks, so anyone on the
+# network can tamper with the binary. Only as a last resort when ca_cert_file can't be used.
+# danger_accept_invalid_certs = false
+
+[diagnostics]
+# Keys whose values are hidden in exported configs and diagnostics, on top of any key named like a
+# key, token, secret or password. Matched case-insensitively against any part of the key name.
+# redact_keys = ["webhook_url"]
+"#;
+
+// An entry of list_config_backups
+#[derive(Serialize)]
+pub struct ConfigBackup {
+    name: String,
+    timestamp: u64,
+}
+
+// <bambooclaw dir>/profiles/<name>. Names are letters, digits, `-` and `_`, so a profile can't
+// point outside profiles/.
+pub(crate) fn profile_dir(name: &str) -> Result<PathBuf, AppError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid profile name '{}': use letters, digits, '-' and '_'",
+            name
+        )));
+    }
+    Ok(bambooclaw_dir()?.join("profiles").join(name))
+}
+
+// config.toml of `profile`, or <bambooclaw dir>/config.toml for the default profile (None).
+// Named profiles have to exist: they are only made by create_profile.
+fn config_path(profile: Option<&str>) -> Result<PathBuf, AppError> {
+    let dir = match profile {
+        Some(name) => {
+            let dir = profile_dir(name)?;
+            if !dir.is_dir() {
+                return Err(AppError::NotFound(format!("No profile named '{}'", name)));
+            }
+            dir
+        }
+        None => bambooclaw_dir()?,
+    };
+    Ok(dir.join("config.toml"))
+}
+
+// Deletes config.toml after backing it up, so the deletion can be undone with
+// restore_config_backup. Returns the path removed, or None if there was no config.
+pub(crate) fn remove_config() -> Result<Option<PathBuf>, AppError> {
+    let path = config_path(None)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    backup_config(&path)?;
+    std::fs::remove_file(&path)
+        .map_err(|e| AppError::io(format!("Failed to remove {}", path.display()), e))?;
+    Ok(Some(path))
+}
+
+// The backups dir kept next to the config at `config_path`
+fn backups_dir(config_path: &Path) -> PathBuf {
+    config_path.with_file_name("backups")
+}
+
+// 5. Read the config.toml file, creating the documented default on first use and upgrading
+// one written by an older version. Every config command takes an optional `profile`: a name
+// from list_profiles, or None for the default config.toml.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub fn read_config(profile: Option<String>) -> Result<String, AppError> {
+    match std::fs::read_to_string(config_path(profile.as_deref())?) {
+        Ok(content) => migrate_content(content, profile.as_deref()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => generate_default_config(profile),
+        Err(e) => Err(AppError::io("Failed to read config.toml", e)),
+    }
+}
+
+// 6. Save the config.toml file. Content that isn't valid TOML is rejected and the
+// existing file is left untouched; valid content replaces it atomically after the
+// previous version has been backed up. With `dry_run` nothing is saved and the reply is the
+// line diff against the current file.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(content), err)]
+pub fn write_config(
+    content: String,
+    profile: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<String, AppError> {
+    if dry_run.unwrap_or(false) {
+        let diff = config_diff(&content, profile.as_deref())?;
+        return Ok(if diff.is_empty() {
+            "No changes".to_string()
+        } else {
+            diff.join("\n")
+        });
+    }
+    save_config(&content, profile.as_deref())?;
+    // The wizard only ever writes the default config
+    if profile.is_none() {
+        complete_step("config_written");
+    }
+    Ok("Config written".to_string())
+}
+
+// What saving `content` would change in config.toml, as `- ` and `+ ` lines; unchanged lines
+// are left out. A missing config.toml counts as empty. Content write_config would refuse is
+// refused here too.
+pub(crate) fn config_diff(content: &str, profile: Option<&str>) -> Result<Vec<String>, AppError> {
+    validate_toml(content).map_err(AppError::InvalidInput)?;
+    let current = match std::fs::read_to_string(config_path(profile)?) {
+        Ok(current) => current,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(AppError::io("Failed to read config.toml", e)),
+    };
+    Ok(line_diff(&current, content))
+}
+
+// The lines removed from `old` and added in `new`, in file order, from their longest common
+// subsequence of lines. Configs are a few hundred lines at most, so the quadratic table is fine.
+fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // common[i][j]: length of the common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || common[i][j + 1] >= common[i + 1][j]) {
+            diff.push(format!("+ {}", new[j]));
+            j += 1;
+        } else {
+            diff.push(format!("- {}", old[i]));
+            i += 1;
+        }
+    }
+    diff
+}
+
+// write_config without marking the wizard's config step done, for configs the user didn't write
+fn save_config(content: &str, profile: Option<&str>) -> Result<(), AppError> {
+    validate_toml(content).map_err(AppError::InvalidInput)?;
+
+    let path = config_path(profile)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::io(format!("Failed to create {}", dir.display()), e))?;
+    }
+
+    backup_config(&path)?;
+    write_atomically(&path, content)
+}
+
+// Writes to a sibling `.tmp` file and renames it over `path`, so an interrupted save can never
+// leave a half-written file behind. The rename is atomic on the same filesystem.
+fn write_atomically(path: &Path, content: &str) -> Result<(), AppError> {
+    let tmp = path.with_extension("toml.tmp");
+    let result = (|| {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)
+    })();
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(AppError::io(
+            format!("Failed to write {}", path.display()),
+            e,
+        ));
+    }
+    Ok(())
+}
+
+// 16. Read a single value by dotted key (e.g. `gateway.port`).
+// Strings come back unquoted; everything else in its TOML form. A missing key is `None`.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn get_config_value(key: String, profile: Option<String>) -> Result<Option<String>, AppError> {
+    let doc = load_document(profile.as_deref())?;
+
+    let mut item = doc.as_item();
+    for part in split_key(&key)? {
+        match item.get(part) {
+            Some(next) => item = next,
+            None => return Ok(None),
+        }
+    }
+
+    Ok(match item {
+        Item::Value(value) => Some(bare_value(value)),
+        other => Some(other.to_string().trim().to_string()),
+    })
+}
+
+// 17. Set a single value by dotted key, leaving the rest of the file as it was: comments, blank
+// lines and key order are kept byte for byte, as is a comment after the replaced value.
+// `value` is parsed as a TOML value (`3000`, `true`, `["a", "b"]`, `"quoted"`); anything that
+// doesn't parse is stored as a plain string. Missing parent tables are created. Setting a key to
+// what get_config_value returned for it changes nothing and doesn't write the file.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(value), err)]
+pub fn set_config_value(
+    key: String,
+    value: String,
+    profile: Option<String>,
+) -> Result<String, AppError> {
+    let parts = split_key(&key)?;
+    let (last, parents) = parts.split_last().unwrap();
+
+    let mut doc = load_document(profile.as_deref())?;
+    let mut table: &mut Table = doc.as_table_mut();
+    for part in parents {
+        let entry = table
+            .entry(part)
+            .or_insert_with(|| Item::Table(Table::new()));
+        table = entry.as_table_mut().ok_or_else(|| {
+            AppError::InvalidInput(format!("'{}' in '{}' is not a table", part, key))
+        })?;
+    }
+
+    let new_value = value
+        .parse::<Value>()
+        .unwrap_or_else(|_| Value::from(value.as_str()));
+    match table.get_mut(last) {
+        Some(Item::Value(existing)) => {
+            if bare_value(existing) == value || value_repr(existing) == value_repr(&new_value) {
+                return Ok(format!("{} is unchanged", key));
+            }
+            // Keep the spacing around the old value and any comment after it
+            let decor = existing.decor().clone();
+            *existing = new_value;
+            *existing.decor_mut() = decor;
+        }
+        _ => table[*last] = Item::Value(new_value),
+    }
+
+    write_config(doc.to_string(), profile, None)?;
+    Ok(format!("Set {}", key))
+}
+
+// 60. Set several keys of one section in a single write, e.g. everything a settings form edits.
+// `section` may be dotted (`channels.telegram`) and is created if missing; keys not in `values`
+// are left as they are, and so are the comments and layout around them, like set_config_value.
+// Keys new to the section are added in alphabetical order, so the same form always writes the
+// same file.
+// Values of the wrong type or out of range for the section are refused and nothing is written;
+// otherwise the file is saved like write_config does, backup included.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(values), err)]
+pub fn set_config_section(
+    section: String,
+    values: BTreeMap<String, toml::Value>,
+    profile: Option<String>,
+) -> Result<String, AppError> {
+    let mut doc = load_document(profile.as_deref())?;
+    let mut table: &mut Table = doc.as_table_mut();
+    for part in split_key(&section)? {
+        let entry = table
+            .entry(part)
+            .or_insert_with(|| Item::Table(Table::new()));
+        table = entry.as_table_mut().ok_or_else(|| {
+            AppError::InvalidInput(format!("'{}' in '{}' is not a table", part, section))
+        })?;
+    }
+
+    for (key, value) in values {
+        let new_value = toml_to_edit(value);
+        match table.get_mut(&key) {
+            Some(Item::Value(existing)) => {
+                let decor = existing.decor().clone();
+                *existing = new_value;
+                *existing.decor_mut() = decor;
+            }
+            _ => table[key.as_str()] = Item::Value(new_value),
+        }
+    }
+
+    let content = doc.to_string();
+    let config = validate_toml(&content).map_err(AppError::InvalidInput)?;
+    let prefix = format!("{}.", section);
+    let problems: Vec<String> = schema::validate(&config)
+        .into_iter()
+        .filter(|warning| !matches!(warning.kind, WarningKind::UnknownKey))
+        .filter(|warning| warning.key == section || warning.key.starts_with(&prefix))
+        .map(|warning| format!("{}: {}", warning.key, warning.message))
+        .collect();
+    if !problems.is_empty() {
+        return Err(AppError::InvalidConfig(problems.join("; ")));
+    }
+
+    write_config(content, profile, None)?;
+    Ok(format!("Updated [{}]", section))
+}
+
+// 18. List config backups, newest first
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub fn list_config_backups(profile: Option<String>) -> Result<Vec<ConfigBackup>, AppError> {
+    collect_backups(&backups_dir(&config_path(profile.as_deref())?))
+}
+
+// 19. Restore a backup listed by list_config_backups. The config being replaced is itself
+// backed up first, so a restore can be undone.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn restore_config_backup(name: String, profile: Option<String>) -> Result<String, AppError> {
+    // Only accept bare backup names, never paths
+    if backup_timestamp(&name).is_none() {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid backup name '{}'",
+            name
+        )));
+    }
+    let dir = backups_dir(&config_path(profile.as_deref())?);
+    let content = std::fs::read_to_string(dir.join(&name))
+        .map_err(|e| AppError::io(format!("Failed to read backup '{}'", name), e))?;
+    write_config(content, profile, None)?;
+    Ok(format!("Restored config from {}", name))
+}
+
+// 20. Write the documented default config.toml and return it. An existing config is
+// backed up first, like any other write.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn generate_default_config(profile: Option<String>) -> Result<String, AppError> {
+    save_config(DEFAULT_CONFIG, profile.as_deref())?;
+    Ok(DEFAULT_CONFIG.to_string())
+}
+
+// 21. Check a config against the schema the daemon expects. Unknown keys, wrong types and
+// out-of-range values come back as warnings for the editor to mark inline; only content that
+// isn't valid TOML at all is an error.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", skip(content), err)]
+pub fn validate_config(content: String) -> Result<Vec<ValidationWarning>, AppError> {
+    let config = validate_toml(&content).map_err(AppError::InvalidInput)?;
+    let mut warnings = schema::validate(&config);
+
+    if let Ok(doc) = ImDocument::parse(content.as_str()) {
+        for warning in &mut warnings {
+            warning.line =
+                key_offset(&doc, &warning.key).map(|offset| line_column(&content, offset).0);
+        }
+    }
+    Ok(warnings)
+}
+
+// 22. Upgrade config.toml to the current schema_version and return it. The pre-migration file
+// is backed up like any other write. read_config does this automatically.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn migrate_config(profile: Option<String>) -> Result<String, AppError> {
+    let content = std::fs::read_to_string(config_path(profile.as_deref())?)
+        .map_err(|e| AppError::io("Failed to read config.toml", e))?;
+    migrate_content(content, profile.as_deref())
+}
+
+// 38. config.toml as pretty-printed JSON, for sharing or for tools that don't speak TOML
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn export_config_to_json(profile: Option<String>) -> Result<String, AppError> {
+    let config = validate_toml(&read_config(profile)?).map_err(AppError::InvalidConfig)?;
+    let json = serde_json::Value::Object(
+        config
+            .into_iter()
+            .map(|(key, value)| (key, toml_to_json(value)))
+            .collect(),
+    );
+    serde_json::to_string_pretty(&json)
+        .map_err(|e| AppError::Other(format!("Failed to serialize the config: {}", e)))
+}
+
+// 39. Replace config.toml with a config given as JSON, as export_config_to_json produces it.
+// Whole numbers stay integers and everything else keeps its type. Values of the wrong type or
+// out of range are refused, as is `null`, which TOML can't express; unknown keys are kept. The
+// file is written like write_config does, so the previous one, comments included, is backed up.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(json), err)]
+pub fn import_config_from_json(json: String, profile: Option<String>) -> Result<String, AppError> {
+    let json: serde_json::Value = serde_json::from_str(&json)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid JSON: {}", e)))?;
+    let toml::Value::Table(config) = json_to_toml(json, "")? else {
+        return Err(AppError::InvalidInput(
+            "The config must be a JSON object".to_string(),
+        ));
+    };
+
+    let problems: Vec<String> = schema::validate(&config)
+        .into_iter()
+        .filter(|warning| !matches!(warning.kind, WarningKind::UnknownKey))
+        .map(|warning| format!("{}: {}", warning.key, warning.message))
+        .collect();
+    if !problems.is_empty() {
+        return Err(AppError::InvalidConfig(problems.join("; ")));
+    }
+
+    let content = toml::to_string(&config)
+        .map_err(|e| AppError::InvalidInput(format!("Failed to convert the config: {}", e)))?;
+    write_config(content, profile, None)?;
+    Ok("Config imported".to_string())
+}
+
+// 49. config.toml with secrets replaced by ***, comments and layout kept, for attaching to bug
+// reports. This is also the config export_diagnostics includes.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn export_redacted_config(profile: Option<String>) -> Result<String, AppError> {
+    redacted_config(profile.as_deref())
+}
+
+// 50. Where config.toml of `profile` lives, for showing to the user. It may not exist yet.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub fn get_config_path(profile: Option<String>) -> Result<String, AppError> {
+    config_path(profile.as_deref()).map(|path| path.to_string_lossy().into_owned())
+}
+
+// 51. Show config.toml of `profile` in the file manager, the same way open_path shows a file. A
+// missing config.toml is written from the defaults first, so there is always something to show.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn open_config_in_file_manager(profile: Option<String>) -> Result<(), AppError> {
+    let path = config_path(profile.as_deref())?;
+    if !path.exists() {
+        generate_default_config(profile)?;
+    }
+
+    crate::open::reveal_in_file_manager(&path)
+}
+
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => serde_json::Value::String(s),
+        toml::Value::Integer(i) => serde_json::Value::from(i),
+        toml::Value::Float(f) => serde_json::Value::from(f),
+        toml::Value::Boolean(b) => serde_json::Value::Bool(b),
+        // JSON has no date type
+        toml::Value::Datetime(d) => serde_json::Value::String(d.to_string()),
+        toml::Value::Array(items) => items.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => serde_json::Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+// A value for a toml_edit document; tables become inline tables
+fn toml_to_edit(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::from(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::from(b),
+        toml::Value::Datetime(d) => Value::from(d),
+        toml::Value::Array(items) => {
+            Value::Array(items.into_iter().map(toml_to_edit).collect::<Array>())
+        }
+        toml::Value::Table(table) => Value::InlineTable(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_edit(value)))
+                .collect::<InlineTable>(),
+        ),
+    }
+}
+
+// `key` is the dotted path of `value`, for errors
+fn json_to_toml(value: serde_json::Value, key: &str) -> Result<toml::Value, AppError> {
+    Ok(match value {
+        serde_json::Value::Null => {
+            return Err(AppError::InvalidInput(format!(
+                "'{}' is null, which a TOML config can't hold; leave the key out instead",
+                key
+            )))
+        }
+        serde_json::Value::Bool(b) => toml::Value::Boolean(b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => toml::Value::Integer(i),
+            None => toml::Value::Float(n.as_f64().ok_or_else(|| {
+                AppError::InvalidInput(format!("'{}' is out of range: {}", key, n))
+            })?),
+        },
+        serde_json::Value::String(s) => toml::Value::String(s),
+        serde_json::Value::Array(items) => toml::Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| json_to_toml(item, &format!("{}[{}]", key, i)))
+                .collect::<Result<_, _>>()?,
+        ),
+        serde_json::Value::Object(map) => toml::Value::Table(
+            map.into_iter()
+                .map(|(name, value)| {
+                    let path = if key.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{}.{}", key, name)
+                    };
+                    json_to_toml(value, &path).map(|value| (name, value))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+    })
+}
+
+// Runs the migrations on `content`, saving the result if anything changed. Content that isn't
+// valid TOML is returned as-is so the user can still open and fix it.
+fn migrate_content(content: String, profile: Option<&str>) -> Result<String, AppError> {
+    let mut doc = match content.parse::<DocumentMut>() {
+        Ok(doc) => doc,
+        Err(_) => return Ok(content),
+    };
+    if !migrate::migrate(&mut doc)? {
+        return Ok(content);
+    }
+    let migrated = doc.to_string();
+    save_config(&migrated, profile)?;
+    Ok(migrated)
+}
+
+// `[shell] allowed_commands` from config.toml: the only commands the frontend may launch.
+// `None` when the config or the key is missing. A config that can't be read or parsed is an
+// error rather than "no restriction", so a broken file never lifts the allowlist.
+pub(crate) fn shell_allowed_commands() -> Result<Option<Vec<String>>, AppError> {
+    let content = match std::fs::read_to_string(config_path(None)?) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(AppError::io("Failed to read config.toml", e)),
+    };
+    let config = validate_toml(&content).map_err(AppError::InvalidConfig)?;
+    let Some(allowed) = config
+        .get("shell")
+        .and_then(|shell| shell.get("allowed_commands"))
+    else {
+        return Ok(None);
+    };
+    allowed
+        .as_array()
+        .and_then(|commands| {
+            commands
+                .iter()
+                .map(|c| c.as_str().map(str::to_string))
+                .collect()
+        })
+        .map(Some)
+        .ok_or_else(|| {
+            AppError::InvalidConfig(
+                "shell.allowed_commands in config.toml must be a list of strings".to_string(),
+            )
+        })
+}
+
+// 45. Named profiles, sorted. The default config.toml isn't one of them.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub fn list_profiles() -> Result<Vec<String>, AppError> {
+    let dir = bambooclaw_dir()?.join("profiles");
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::io(format!("Failed to read {}", dir.display()), e)),
+    };
+    let mut profiles: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| profile_dir(name).is_ok())
+        .collect();
+    profiles.sort();
+    Ok(profiles)
+}
+
+// 46. Create a profile with the documented default config, e.g. "staging". Names are letters,
+// digits, `-` and `_`; an existing profile is left alone and reported as InvalidInput.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn create_profile(name: String) -> Result<String, AppError> {
+    let dir = profile_dir(&name)?;
+    if dir.exists() {
+        return Err(AppError::InvalidInput(format!(
+            "Profile '{}' already exists",
+            name
+        )));
+    }
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AppError::io(format!("Failed to create {}", dir.display()), e))?;
+    write_atomically(&dir.join("config.toml"), DEFAULT_CONFIG)?;
+    Ok(format!("Created profile '{}'", name))
+}
+
+// 47. Delete a profile along with its config backups. The profile the daemon is running with
+// can't be deleted until the daemon is stopped.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(state), err)]
+pub fn delete_profile(state: tauri::State<DaemonState>, name: String) -> Result<String, AppError> {
+    let dir = profile_dir(&name)?;
+    if !dir.is_dir() {
+        return Err(AppError::NotFound(format!("No profile named '{}'", name)));
+    }
+    if running_profile(&state).as_deref() == Some(name.as_str()) {
+        return Err(AppError::InvalidInput(format!(
+            "Profile '{}' is in use by the running daemon; stop it first",
+            name
+        )));
+    }
+    std::fs::remove_dir_all(&dir)
+        .map_err(|e| AppError::io(format!("Failed to remove {}", dir.display()), e))?;
+    Ok(format!("Deleted profile '{}'", name))
+}
+
+// 23. The agent's temp dir (see agent_temp_dir), created if needed
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub fn get_agent_temp_dir() -> Result<String, AppError> {
+    let dir = agent_temp_dir();
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AppError::io(format!("Failed to create {}", dir.display()), e))?;
+    Ok(dir.display().to_string())
+}
+
+// `[name]` of config.toml as a `T`; `T::default()` when the config or the section is missing
+pub(crate) fn read_section<T: DeserializeOwned + Default>(name: &str) -> Result<T, AppError> {
+    read_profile_section(name, None)
+}
+
+// read_section for the config of `profile`
+pub(crate) fn read_profile_section<T: DeserializeOwned + Default>(
+    name: &str,
+    profile: Option<&str>,
+) -> Result<T, AppError> {
+    let content = match std::fs::read_to_string(config_path(profile)?) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(e) => return Err(AppError::io("Failed to read config.toml", e)),
+    };
+    match validate_toml(&content)
+        .map_err(AppError::InvalidConfig)?
+        .remove(name)
+    {
+        Some(section) => section.try_into().map_err(|e: toml::de::Error| {
+            AppError::InvalidConfig(format!(
+                "Invalid [{}] in config.toml: {}",
+                name,
+                e.message()
+            ))
+        }),
+        None => Ok(T::default()),
+    }
+}
+
+// Where the agent keeps temporary files: `[agent] temp_dir`, or `bambooclaw` in the system
+// temp directory
+pub(crate) fn agent_temp_dir() -> PathBuf {
+    #[derive(Deserialize, Default)]
+    struct AgentPaths {
+        temp_dir: Option<String>,
+    }
+    read_section::<AgentPaths>("agent")
+        .ok()
+        .and_then(|agent| agent.temp_dir)
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("bambooclaw"))
+}
+
+// `path` as Windows programs see it, e.g. \\wsl.localhost\Ubuntu\home\..., when running under
+// WSL; None elsewhere, including on Windows and macOS, or when wslpath can't translate it
+pub(crate) fn wsl_windows_path(path: &Path) -> Option<String> {
+    if !crate::is_wsl() {
+        return None;
+    }
+    let output = std::process::Command::new("wslpath")
+        .arg("-w")
+        .arg(path)
+        .output()
+        .ok()?;
+    let translated = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !translated.is_empty()).then_some(translated)
+}
+
+// config.toml with every secret replaced by ***, for bug reports. A value counts as secret
+// when its key, or the table it sits in, is named like a key, token, secret or password, or like
+// one of the words in `[diagnostics] redact_keys`; this covers `[llm] api_key`, every entry of
+// `[llm_keys]` and channel bot tokens.
+pub(crate) fn redacted_config(profile: Option<&str>) -> Result<String, AppError> {
+    let mut doc = load_document(profile)?;
+    let words = secret_key_words(&doc);
+    redact_table(doc.as_table_mut(), false, &words);
+    Ok(doc.to_string())
+}
+
+// SECRET_KEY_WORDS plus the config's own redact_keys, lowercased. Entries that aren't strings
+// are skipped here; validate_config reports them.
+fn secret_key_words(doc: &DocumentMut) -> Vec<String> {
+    let configured = doc
+        .get("diagnostics")
+        .and_then(|section| section.get("redact_keys"))
+        .and_then(Item::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|word| word.as_str())
+        .filter(|word| !word.trim().is_empty())
+        .map(|word| word.trim().to_lowercase());
+    SECRET_KEY_WORDS
+        .iter()
+        .map(|word| word.to_string())
+        .chain(configured)
+        .collect()
+}
+
+fn redact_table(table: &mut Table, secret: bool, words: &[String]) {
+    for (key, item) in table.iter_mut() {
+        let secret = secret || is_secret_key(key.get(), words);
+        match item {
+            Item::Value(value) => redact_value(value, secret, words),
+            Item::Table(table) => redact_table(table, secret, words),
+            Item::ArrayOfTables(tables) => {
+                for table in tables.iter_mut() {
+                    redact_table(table, secret, words);
+                }
+            }
+            Item::None => {}
+        }
+    }
+}
+
+fn redact_value(value: &mut Value, secret: bool, words: &[String]) {
+    match value {
+        // Empty strings stay visible: "not set" is useful to know
+        Value::String(s) if secret && !s.value().is_empty() => {
+            let decor = s.decor().clone();
+            *value = Value::from(REDACTED);
+            *value.decor_mut() = decor;
+        }
+        Value::Array(array) => {
+            for value in array.iter_mut() {
+                redact_value(value, secret, words);
+            }
+        }
+        Value::InlineTable(table) => {
+            for (key, value) in table.iter_mut() {
+                let secret = secret || is_secret_key(key.get(), words);
+                redact_value(value, secret, words);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_secret_key(key: &str, words: &[String]) -> bool {
+    let key = key.to_lowercase();
+    words.iter().any(|word| key.contains(word.as_str()))
+}
+
+// Copies the current config to backups/config-<unix_ts>.toml and prunes the oldest backups. A
+// second backup within the same second gets a `-1`, `-2`... suffix instead of replacing the first.
+fn backup_config(path: &Path) -> Result<(), AppError> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let dir = backups_dir(path);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AppError::io(format!("Failed to create {}", dir.display()), e))?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let copy_failed = |e| AppError::io("Failed to back up config.toml", e);
+    let mut source = std::fs::File::open(path).map_err(copy_failed)?;
+    let mut backup = (0u32..)
+        .map(|n| match n {
+            0 => dir.join(format!("config-{}.toml", timestamp)),
+            n => dir.join(format!("config-{}-{}.toml", timestamp, n)),
+        })
+        .find_map(|name| {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(name)
+            {
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => None,
+                result => Some(result),
+            }
+        })
+        .expect("unbounded range")
+        .map_err(copy_failed)?;
+    std::io::copy(&mut source, &mut backup).map_err(copy_failed)?;
+
+    for stale in collect_backups(&dir)?.iter().skip(MAX_CONFIG_BACKUPS) {
+        let _ = std::fs::remove_file(dir.join(&stale.name));
+    }
+    Ok(())
+}
+
+// Backups in `dir`, newest first
+fn collect_backups(dir: &Path) -> Result<Vec<ConfigBackup>, AppError> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::io(format!("Failed to read {}", dir.display()), e)),
+    };
+
+    let mut backups: Vec<ConfigBackup> = entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            let timestamp = backup_timestamp(&name)?;
+            Some(ConfigBackup { name, timestamp })
+        })
+        .collect();
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup_order(&backup.name)));
+    Ok(backups)
+}
+
+// The timestamp in a `config-<unix_ts>.toml` or `config-<unix_ts>-<n>.toml` name; `None` for
+// anything else
+fn backup_timestamp(name: &str) -> Option<u64> {
+    backup_order(name).map(|(timestamp, _)| timestamp)
+}
+
+// The timestamp and suffix of a backup name, which order backups oldest first
+fn backup_order(name: &str) -> Option<(u64, u32)> {
+    let stem = name.strip_prefix("config-")?.strip_suffix(".toml")?;
+    match stem.split_once('-') {
+        Some((timestamp, n)) => Some((timestamp.parse().ok()?, n.parse().ok()?)),
+        None => Some((stem.parse().ok()?, 0)),
+    }
+}
+
+// The current config.toml as an editable document; a missing file is an empty document
+fn load_document(profile: Option<&str>) -> Result<DocumentMut, AppError> {
+    let content = match std::fs::read_to_string(config_path(profile)?) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(AppError::io("Failed to read config.toml", e)),
+    };
+    content
+        .parse::<DocumentMut>()
+        .map_err(|e| AppError::InvalidConfig(format!("config.toml is not valid TOML: {}", e)))
+}
+
+// Parses `content` as TOML, describing the first syntax error with its line and column
+fn validate_toml(content: &str) -> Result<toml::Table, String> {
+    let err = match content.parse::<toml::Table>() {
+        Ok(table) => return Ok(table),
+        Err(err) => err,
+    };
+    match err.span() {
+        Some(span) => {
+            let (line, column) = line_column(content, span.start);
+            Err(format!(
+                "Invalid TOML at line {}, column {}: {}",
+                line,
+                column,
+                err.message()
+            ))
+        }
+        None => Err(format!("Invalid TOML: {}", err.message())),
+    }
+}
+
+// 1-based line and column of a byte offset into `content`
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = content.get(..offset).unwrap_or(content);
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
+// Byte offset where a dotted key is written in `doc`
+fn key_offset(doc: &ImDocument<&str>, key: &str) -> Option<usize> {
+    let mut item = doc.as_item();
+    let mut offset = None;
+    for part in key.split('.') {
+        let (found, next) = item.as_table_like()?.get_key_value(part)?;
+        offset = found.span().map(|span| span.start);
+        item = next;
+    }
+    offset
+}
+
+// A value as get_config_value shows it: strings unquoted, anything else in its TOML form
+fn bare_value(value: &Value) -> String {
+    match value.as_str() {
+        Some(s) => s.to_string(),
+        None => value_repr(value),
+    }
+}
+
+// How `value` is written in the file, without the whitespace or comment around it
+fn value_repr(value: &Value) -> String {
+    let mut value = value.clone();
+    value.decor_mut().clear();
+    value.to_string()
+}
+
+fn split_key(key: &str) -> Result<Vec<&str>, AppError> {
+    let parts: Vec<&str> = key.split('.').map(str::trim).collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid config key '{}'",
+            key
+        )));
+    }
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Backups made within the same second must not replace each other
+    #[test]
+    fn backups_in_the_same_second_are_all_kept() {
+        let dir = std::env::temp_dir().join(format!("bambooclaw-{}-backups", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "first = 1\n").unwrap();
+        backup_config(&path).unwrap();
+        std::fs::write(&path, "second = 2\n").unwrap();
+        backup_config(&path).unwrap();
+
+        let backups = collect_backups(&backups_dir(&path)).unwrap();
+        assert_eq!(backups.len(), 2);
+        let read = |backup: &ConfigBackup| {
+            std::fs::read_to_string(backups_dir(&path).join(&backup.name)).unwrap()
+        };
+        // Newest first
+        assert_eq!(read(&backups[0]), "second = 2\n");
+        assert_eq!(read(&backups[1]), "first = 1\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
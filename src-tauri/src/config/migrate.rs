@@ -0,0 +1,87 @@
+// Upgrades of older config.toml layouts. Every config carries a top-level `schema_version`;
+// one without it predates versioning and counts as version 0. Changing the layout means
+// bumping CURRENT_SCHEMA_VERSION and adding a step to MIGRATIONS.
+
+use crate::error::AppError;
+use toml_edit::{value, DocumentMut, Item, Table};
+
+pub const CURRENT_SCHEMA_VERSION: i64 = 1;
+
+// Upgrades a config by one version, in place
+type Migration = fn(&mut DocumentMut);
+
+// Each step upgrades a config from the version it is keyed by to the next one
+const MIGRATIONS: &[(i64, Migration)] = &[(0, v0_to_v1)];
+
+// Applies the steps between the config's version and the current one, in order. Returns
+// whether anything changed; a config written by a newer app is left alone.
+pub fn migrate(doc: &mut DocumentMut) -> Result<bool, AppError> {
+    let mut version = match doc.get("schema_version") {
+        None => 0,
+        Some(item) => item.as_integer().ok_or_else(|| {
+            AppError::InvalidConfig("schema_version must be an integer".to_string())
+        })?,
+    };
+    if version >= CURRENT_SCHEMA_VERSION {
+        return Ok(false);
+    }
+
+    while version < CURRENT_SCHEMA_VERSION {
+        let (_, step) = MIGRATIONS
+            .iter()
+            .find(|(from, _)| *from == version)
+            .ok_or_else(|| {
+                AppError::InvalidConfig(format!(
+                    "No config migration from schema_version {}",
+                    version
+                ))
+            })?;
+        step(doc);
+        version += 1;
+    }
+    doc["schema_version"] = value(version);
+    Ok(true)
+}
+
+// v1: API keys and local URLs are kept per provider in [llm_keys] / [llm_local_urls], and
+// `agent.loglevel` is spelled `agent.log_level`
+fn v0_to_v1(doc: &mut DocumentMut) {
+    if let Some(agent) = doc.get_mut("agent").and_then(Item::as_table_mut) {
+        if let Some(level) = agent.remove("loglevel") {
+            agent.entry("log_level").or_insert(level);
+        }
+    }
+
+    let llm = doc.get("llm");
+    let provider = llm
+        .and_then(|llm| llm.get("provider"))
+        .and_then(Item::as_str);
+    let api_key = llm
+        .and_then(|llm| llm.get("api_key"))
+        .and_then(Item::as_str);
+    let local_url = llm
+        .and_then(|llm| llm.get("local_url"))
+        .and_then(Item::as_str);
+    let Some(provider) = provider.map(str::to_string) else {
+        return;
+    };
+    let api_key = api_key.filter(|s| !s.is_empty()).map(str::to_string);
+    let local_url = local_url.filter(|s| !s.is_empty()).map(str::to_string);
+
+    if let Some(api_key) = api_key {
+        seed_provider_entry(doc, "llm_keys", &provider, &api_key);
+    }
+    if let Some(local_url) = local_url {
+        seed_provider_entry(doc, "llm_local_urls", &provider, &local_url);
+    }
+}
+
+// Sets `[section] provider = entry` unless the provider already has an entry there
+fn seed_provider_entry(doc: &mut DocumentMut, section: &str, provider: &str, entry: &str) {
+    let table = doc
+        .entry(section)
+        .or_insert_with(|| Item::Table(Table::new()));
+    if let Some(table) = table.as_table_mut() {
+        table.entry(provider).or_insert(value(entry));
+    }
+}
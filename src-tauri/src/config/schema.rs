@@ -0,0 +1,375 @@
+// The config.toml layout the daemon and this app expect. Each section is a serde struct, so the
+// keys and their types live in one place; validate() checks a parsed config against them and
+// collects warnings instead of stopping at the first problem.
+
+use super::migrate::CURRENT_SCHEMA_VERSION;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+const AUTONOMY_LEVELS: &[&str] = &["observe", "collaborative", "autonomous"];
+const LOG_LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    UnknownKey,
+    TypeMismatch,
+    OutOfRange,
+}
+
+// A single problem found by validate_config. `key` is dotted (e.g. `gateway.port`); `line` is
+// 1-based and filled in by the caller when the key can be located in the source.
+#[derive(Serialize)]
+pub struct ValidationWarning {
+    pub key: String,
+    pub kind: WarningKind,
+    pub message: String,
+    pub line: Option<usize>,
+}
+
+impl ValidationWarning {
+    fn new(key: &str, kind: WarningKind, message: String) -> Self {
+        ValidationWarning {
+            key: key.to_string(),
+            kind,
+            message,
+            line: None,
+        }
+    }
+}
+
+// A section whose keys are described by a struct. Keys the struct doesn't name end up in
+// `unknown`; check_values covers constraints the types alone can't express.
+trait Section: DeserializeOwned {
+    fn unknown(&self) -> &toml::Table;
+
+    fn check_values(&self, _name: &str, _warnings: &mut Vec<ValidationWarning>) {}
+}
+
+// [llm]
+// Fields are only deserialized to type-check the keys; not all of them are range-checked.
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct LlmSection {
+    provider: Option<String>,
+    model: Option<String>,
+    api_key: Option<String>,
+    local_url: Option<String>,
+    #[serde(flatten)]
+    unknown: toml::Table,
+}
+
+impl Section for LlmSection {
+    fn unknown(&self) -> &toml::Table {
+        &self.unknown
+    }
+
+    fn check_values(&self, name: &str, warnings: &mut Vec<ValidationWarning>) {
+        if let Some(url) = &self.local_url {
+            check_url(&format!("{}.local_url", name), url, warnings);
+        }
+    }
+}
+
+// [agent]
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct AgentSection {
+    autonomy: Option<String>,
+    identity: Option<String>,
+    log_level: Option<String>,
+    composio_api_key: Option<String>,
+    temp_dir: Option<String>,
+    #[serde(flatten)]
+    unknown: toml::Table,
+}
+
+impl Section for AgentSection {
+    fn unknown(&self) -> &toml::Table {
+        &self.unknown
+    }
+
+    fn check_values(&self, name: &str, warnings: &mut Vec<ValidationWarning>) {
+        if let Some(autonomy) = &self.autonomy {
+            check_one_of(
+                &format!("{}.autonomy", name),
+                autonomy,
+                AUTONOMY_LEVELS,
+                warnings,
+            );
+        }
+        if let Some(level) = &self.log_level {
+            check_one_of(&format!("{}.log_level", name), level, LOG_LEVELS, warnings);
+        }
+    }
+}
+
+// [gateway]
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct GatewaySection {
+    host: Option<String>,
+    port: Option<i64>,
+    #[serde(flatten)]
+    unknown: toml::Table,
+}
+
+impl Section for GatewaySection {
+    fn unknown(&self) -> &toml::Table {
+        &self.unknown
+    }
+
+    fn check_values(&self, name: &str, warnings: &mut Vec<ValidationWarning>) {
+        if let Some(port) = self.port {
+            if !(1..=65535).contains(&port) {
+                warnings.push(ValidationWarning::new(
+                    &format!("{}.port", name),
+                    WarningKind::OutOfRange,
+                    format!("Port must be between 1 and 65535, got {}", port),
+                ));
+            }
+        }
+    }
+}
+
+// [shell]
+#[derive(Deserialize)]
+struct ShellSection {
+    // Only read by the shell commands themselves; listed here so the key is known
+    #[allow(dead_code)]
+    allowed_commands: Option<Vec<String>>,
+    #[serde(flatten)]
+    unknown: toml::Table,
+}
+
+impl Section for ShellSection {
+    fn unknown(&self) -> &toml::Table {
+        &self.unknown
+    }
+}
+
+// [daemon]
+#[derive(Deserialize)]
+struct DaemonSection {
+    // Only read when the daemon starts; listed here so the key is known
+    #[allow(dead_code)]
+    args: Option<Vec<String>>,
+    ready_timeout_secs: Option<i64>,
+    ready_poll_interval_ms: Option<i64>,
+    #[allow(dead_code)]
+    stop_daemon_on_exit: Option<bool>,
+    #[serde(flatten)]
+    unknown: toml::Table,
+}
+
+impl Section for DaemonSection {
+    fn unknown(&self) -> &toml::Table {
+        &self.unknown
+    }
+
+    fn check_values(&self, name: &str, warnings: &mut Vec<ValidationWarning>) {
+        let durations = [
+            ("ready_timeout_secs", self.ready_timeout_secs),
+            ("ready_poll_interval_ms", self.ready_poll_interval_ms),
+        ];
+        for (key, value) in durations {
+            if let Some(value) = value.filter(|value| *value < 1) {
+                warnings.push(ValidationWarning::new(
+                    &format!("{}.{}", name, key),
+                    WarningKind::OutOfRange,
+                    format!("Must be at least 1, got {}", value),
+                ));
+            }
+        }
+    }
+}
+
+// [emergency]
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct EmergencySection {
+    process_names: Option<Vec<String>>,
+    temp_patterns: Option<Vec<String>>,
+    #[serde(flatten)]
+    unknown: toml::Table,
+}
+
+impl Section for EmergencySection {
+    fn unknown(&self) -> &toml::Table {
+        &self.unknown
+    }
+}
+
+// [downloads]
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct DownloadsSection {
+    allowed_dirs: Option<Vec<String>>,
+    ca_cert_file: Option<String>,
+    danger_accept_invalid_certs: Option<bool>,
+    #[serde(flatten)]
+    unknown: toml::Table,
+}
+
+impl Section for DownloadsSection {
+    fn unknown(&self) -> &toml::Table {
+        &self.unknown
+    }
+}
+
+// [diagnostics]
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct DiagnosticsSection {
+    redact_keys: Option<Vec<String>>,
+    #[serde(flatten)]
+    unknown: toml::Table,
+}
+
+impl Section for DiagnosticsSection {
+    fn unknown(&self) -> &toml::Table {
+        &self.unknown
+    }
+}
+
+// Checks a whole parsed config.toml
+pub fn validate(config: &toml::Table) -> Vec<ValidationWarning> {
+    let mut warnings = Vec::new();
+    for (name, value) in config {
+        match name.as_str() {
+            "schema_version" => check_schema_version(name, value, &mut warnings),
+            "llm" => check_section::<LlmSection>(name, value, &mut warnings),
+            "agent" => check_section::<AgentSection>(name, value, &mut warnings),
+            "gateway" => check_section::<GatewaySection>(name, value, &mut warnings),
+            "shell" => check_section::<ShellSection>(name, value, &mut warnings),
+            "daemon" => check_section::<DaemonSection>(name, value, &mut warnings),
+            "emergency" => check_section::<EmergencySection>(name, value, &mut warnings),
+            "downloads" => check_section::<DownloadsSection>(name, value, &mut warnings),
+            "diagnostics" => check_section::<DiagnosticsSection>(name, value, &mut warnings),
+            // Per-provider API keys and local URLs, keyed by provider name
+            "llm_keys" | "llm_local_urls" => check_string_map(name, value, &mut warnings),
+            // One table per channel; each channel defines its own keys
+            "channels" => check_channels(name, value, &mut warnings),
+            _ => warnings.push(unknown_key(name)),
+        }
+    }
+    warnings
+}
+
+// Deserializes every key on its own, so one bad key doesn't hide problems in the others
+fn check_section<T: Section>(
+    name: &str,
+    value: &toml::Value,
+    warnings: &mut Vec<ValidationWarning>,
+) {
+    let Some(table) = as_table(name, value, warnings) else {
+        return;
+    };
+
+    let mut valid = toml::Table::new();
+    for (key, value) in table {
+        let path = format!("{}.{}", name, key);
+        let single = toml::Table::from_iter([(key.clone(), value.clone())]);
+        match toml::Value::Table(single).try_into::<T>() {
+            Ok(section) if !section.unknown().is_empty() => warnings.push(unknown_key(&path)),
+            Ok(_) => {
+                valid.insert(key.clone(), value.clone());
+            }
+            Err(e) => warnings.push(ValidationWarning::new(
+                &path,
+                WarningKind::TypeMismatch,
+                e.message().trim().to_string(),
+            )),
+        }
+    }
+
+    if let Ok(section) = toml::Value::Table(valid).try_into::<T>() {
+        section.check_values(name, warnings);
+    }
+}
+
+fn check_schema_version(name: &str, value: &toml::Value, warnings: &mut Vec<ValidationWarning>) {
+    match value.as_integer() {
+        Some(version) if (0..=CURRENT_SCHEMA_VERSION).contains(&version) => {}
+        Some(version) => warnings.push(ValidationWarning::new(
+            name,
+            WarningKind::OutOfRange,
+            format!(
+                "schema_version {} is not supported by this version of the app (latest is {})",
+                version, CURRENT_SCHEMA_VERSION
+            ),
+        )),
+        None => warnings.push(type_mismatch(name, "an integer", value)),
+    }
+}
+
+fn check_string_map(name: &str, value: &toml::Value, warnings: &mut Vec<ValidationWarning>) {
+    let Some(table) = as_table(name, value, warnings) else {
+        return;
+    };
+    for (key, value) in table {
+        let path = format!("{}.{}", name, key);
+        match value.as_str() {
+            Some(url) if name == "llm_local_urls" => check_url(&path, url, warnings),
+            Some(_) => {}
+            None => warnings.push(type_mismatch(&path, "a string", value)),
+        }
+    }
+}
+
+fn check_channels(name: &str, value: &toml::Value, warnings: &mut Vec<ValidationWarning>) {
+    let Some(table) = as_table(name, value, warnings) else {
+        return;
+    };
+    for (channel, value) in table {
+        as_table(&format!("{}.{}", name, channel), value, warnings);
+    }
+}
+
+fn as_table<'a>(
+    key: &str,
+    value: &'a toml::Value,
+    warnings: &mut Vec<ValidationWarning>,
+) -> Option<&'a toml::Table> {
+    let table = value.as_table();
+    if table.is_none() {
+        warnings.push(type_mismatch(key, "a table", value));
+    }
+    table
+}
+
+fn check_one_of(key: &str, value: &str, allowed: &[&str], warnings: &mut Vec<ValidationWarning>) {
+    if !allowed.contains(&value) {
+        warnings.push(ValidationWarning::new(
+            key,
+            WarningKind::OutOfRange,
+            format!("Expected one of {}, got \"{}\"", allowed.join(", "), value),
+        ));
+    }
+}
+
+fn check_url(key: &str, url: &str, warnings: &mut Vec<ValidationWarning>) {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        warnings.push(ValidationWarning::new(
+            key,
+            WarningKind::OutOfRange,
+            format!("Expected an http:// or https:// URL, got \"{}\"", url),
+        ));
+    }
+}
+
+fn unknown_key(key: &str) -> ValidationWarning {
+    ValidationWarning::new(
+        key,
+        WarningKind::UnknownKey,
+        format!("Unknown key '{}'", key),
+    )
+}
+
+fn type_mismatch(key: &str, expected: &str, value: &toml::Value) -> ValidationWarning {
+    ValidationWarning::new(
+        key,
+        WarningKind::TypeMismatch,
+        format!("Expected {}, found {}", expected, value.type_str()),
+    )
+}
@@ -0,0 +1,1289 @@
+use crate::config::{profile_dir, read_config, read_profile_section};
+use crate::error::AppError;
+use crate::flush::descendants;
+use crate::progress::{emit_progress, ProgressEvent};
+use crate::{bambooclaw_dir, run_checked, ShellOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, Process, Signal, System, MINIMUM_CPU_UPDATE_INTERVAL};
+use tauri::Manager;
+
+// The daemon's gateway listens on `[gateway] port` from config.toml, 3000 unless overridden
+const DEFAULT_GATEWAY_PORT: u16 = 3000;
+const PING_TIMEOUT: Duration = Duration::from_secs(3);
+pub(crate) const DEFAULT_GRACEFUL_TIMEOUT: Duration = Duration::from_secs(10);
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// How long stop_daemon waits for a killed daemon to be gone before giving up
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+// logs/daemon.log is rotated to daemon.log.1 once it grows past this size; daemon.log.1 moves
+// to .2 and so on, and the oldest beyond DAEMON_LOG_KEEP is deleted
+const DAEMON_LOG_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const DAEMON_LOG_KEEP: usize = 5;
+// How long start_daemon waits for a new daemon to answer health checks, and how often it asks,
+// unless `[daemon] ready_timeout_secs` and `ready_poll_interval_ms` say otherwise
+const READY_TIMEOUT: Duration = Duration::from_secs(20);
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(250);
+// How long reload_daemon_config waits for the daemon to report that it re-read its config
+const RELOAD_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+// How often a spawned daemon is checked for an unexpected exit
+const MONITOR_INTERVAL: Duration = Duration::from_secs(1);
+// How long the daemon gets to exit on its own when the app quits with `stop_daemon_on_exit`
+const EXIT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Held by start_daemon, stop_daemon and restart_daemon while they start or stop the daemon. The
+// DaemonState lock can't be held across an await, so without this a start could slip in while a
+// restart waits for the old daemon to exit, and then find it or race the restart's own spawn.
+static LIFECYCLE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+// A daemon process spawned by this session
+pub struct ManagedDaemon {
+    pub child: Child,
+    pub started_at: Instant,
+    // Passed after `daemon`; restarts reuse them
+    pub args: Vec<String>,
+    // The config profile it runs with, None for the default config.toml; restarts reuse it too
+    pub profile: Option<String>,
+}
+
+// `[daemon]` in config.toml
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct DaemonConfig {
+    // Used by start_daemon when it is given no arguments, e.g. ["--port", "3100"]
+    args: Vec<String>,
+    ready_timeout_secs: Option<u64>,
+    ready_poll_interval_ms: Option<u64>,
+    // Whether quitting the app stops the daemon; by default it keeps running
+    stop_daemon_on_exit: bool,
+}
+
+// How long to wait for a new daemon to become ready, and how often to check
+#[derive(Clone, Copy)]
+struct Readiness {
+    timeout: Duration,
+    poll_interval: Duration,
+}
+
+impl DaemonConfig {
+    fn readiness(&self) -> Readiness {
+        Readiness {
+            timeout: self
+                .ready_timeout_secs
+                .filter(|secs| *secs > 0)
+                .map_or(READY_TIMEOUT, Duration::from_secs),
+            poll_interval: self
+                .ready_poll_interval_ms
+                .filter(|ms| *ms > 0)
+                .map_or(READY_POLL_INTERVAL, Duration::from_millis),
+        }
+    }
+}
+
+// State manager to keep track of the running background daemon
+pub struct DaemonState(pub Mutex<Option<ManagedDaemon>>);
+
+// Whether daemon output is also streamed to the UI; read when the daemon is spawned
+#[derive(Default)]
+pub struct DaemonLogCapture(AtomicBool);
+
+// Payload of the `daemon_log` event
+#[derive(Clone, Serialize)]
+struct DaemonLogLine {
+    stream: &'static str,
+    line: String,
+}
+
+// Reply of daemon_status
+#[derive(Serialize)]
+pub struct DaemonStatus {
+    running: bool,
+    pid: Option<u32>,
+    uptime_secs: Option<u64>,
+}
+
+// Reply of get_daemon_resource_usage: the daemon and every process below it, added together
+#[derive(Serialize)]
+pub struct ResourceUsage {
+    // Share of one core, so a busy multi-threaded tree can exceed 100
+    cpu_percent: f32,
+    memory_bytes: u64,
+    // Only counted on Linux; elsewhere each process counts as a single thread
+    thread_count: usize,
+}
+
+// Payload of the `daemon_restarted` event
+#[derive(Clone, Serialize)]
+struct DaemonRestarted {
+    pid: u32,
+}
+
+// Auto-restart policy applied when the daemon exits with a failure status.
+// At most `max_restarts` restarts happen within any `window_secs` window to avoid crash loops.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WatchdogPolicy {
+    enabled: bool,
+    max_restarts: u32,
+    window_secs: u64,
+}
+
+impl Default for WatchdogPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_restarts: 3,
+            window_secs: 60,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Watchdog {
+    policy: WatchdogPolicy,
+    // When recent auto-restarts happened, oldest first
+    restarts: VecDeque<Instant>,
+}
+
+#[derive(Default)]
+pub struct WatchdogState(Mutex<Watchdog>);
+
+// Payload of the `daemon_exited` event, sent whenever the daemon process ends. `expected` is
+// true when the app stopped it. `signal` is the Unix signal that killed it, if any; neither code
+// is known for a daemon from an earlier session.
+#[derive(Clone, Serialize)]
+struct DaemonExited {
+    pid: u32,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+    expected: bool,
+}
+
+// A `daemon` progress event, sent each time a starting daemon is polled for readiness and once
+// more when it answers
+#[derive(Clone, Serialize)]
+pub(crate) struct DaemonProgress {
+    pid: u32,
+    elapsed_secs: u64,
+    timeout_secs: u64,
+    ready: bool,
+}
+
+// Payload of the `daemon_ready` event
+#[derive(Clone, Serialize)]
+struct DaemonReady {
+    pid: u32,
+}
+
+// Payload of the `daemon_failed_to_start` event
+#[derive(Clone, Serialize)]
+struct DaemonFailedToStart {
+    pid: u32,
+    message: String,
+}
+
+// Payload of the `daemon_crashed` event
+#[derive(Clone, Serialize)]
+struct DaemonCrashed {
+    pid: u32,
+    exit_code: Option<i32>,
+}
+
+// Payload of the `daemon_auto_restarted` event
+#[derive(Clone, Serialize)]
+struct DaemonAutoRestarted {
+    pid: u32,
+    restarts_in_window: usize,
+}
+
+// 8. Start the BambooClaw background daemon (HEADLESS). `extra_args` (e.g. ["--port", "3100"])
+// are passed after `daemon`; without them, `[daemon] args` from config.toml is used. `profile`
+// runs it with that profile's config.toml instead of the default one.
+// Unless `wait_ready` is false, only returns once the daemon answers health checks; a daemon that
+// exits or stays unreachable for READY_TIMEOUT is killed and reported as an error. With
+// `wait_ready: false` it returns once the daemon is spawned, and the same check runs in the
+// background: `daemon_ready` is emitted when the daemon answers, `daemon_failed_to_start` (with
+// the error message) when it exits or times out.
+// A daemon already running outside this session, recorded in daemon.pid or found among running
+// processes (e.g. started from a terminal), is reported as DaemonAlreadyRunning; with
+// `adopt_existing` it is adopted as adopt_existing_daemon does and nothing new is started.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(app, state, capture), err)]
+pub async fn start_daemon(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, DaemonState>,
+    capture: tauri::State<'_, DaemonLogCapture>,
+    extra_args: Option<Vec<String>>,
+    wait_ready: Option<bool>,
+    profile: Option<String>,
+    adopt_existing: Option<bool>,
+) -> Result<String, AppError> {
+    let readiness = read_profile_section::<DaemonConfig>("daemon", profile.as_deref())
+        .unwrap_or_default()
+        .readiness();
+    let lifecycle = LIFECYCLE_LOCK.lock().await;
+    // The scan reads every process, so it runs before the daemon state is locked; the lifecycle
+    // lock already keeps other starts and stops out meanwhile
+    let existing =
+        tauri::async_runtime::spawn_blocking(|| recorded_daemon_pid().or_else(find_daemon_process))
+            .await
+            .map_err(|e| AppError::Other(format!("Task join error: {}", e)))?;
+    let pid = {
+        let mut child_guard = state.0.lock().unwrap();
+        if child_guard.is_some() {
+            return Err(AppError::DaemonAlreadyRunning(
+                "Daemon is already running".to_string(),
+            ));
+        }
+        if let Some(pid) = existing {
+            if !adopt_existing.unwrap_or(false) {
+                return Err(AppError::DaemonAlreadyRunning(format!(
+                    "Daemon is already running (pid {}), started outside this session",
+                    pid
+                )));
+            }
+            adopt(pid)?;
+            return Ok(format!("Adopted the daemon already running (pid {})", pid));
+        }
+
+        let args = daemon_args(extra_args, profile.as_deref())?;
+        let daemon = spawn_daemon(&app, capture.0.load(Ordering::Relaxed), args, profile)?;
+        let pid = daemon.child.id();
+        *child_guard = Some(daemon);
+        pid
+    };
+    monitor_daemon(app.clone(), pid);
+    // A stop may come in while the daemon is still getting ready
+    drop(lifecycle);
+
+    if wait_ready.unwrap_or(true) {
+        wait_until_ready(&app, &state, pid, readiness).await?;
+        return Ok("Daemon started".to_string());
+    }
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<DaemonState>();
+        match wait_until_ready(&app, &state, pid, readiness).await {
+            Ok(()) => {
+                let _ = app.emit_all("daemon_ready", DaemonReady { pid });
+            }
+            Err(e) => {
+                let message = e.to_string();
+                let _ = app.emit_all(
+                    "daemon_failed_to_start",
+                    DaemonFailedToStart { pid, message },
+                );
+            }
+        }
+    });
+    Ok("Daemon started".to_string())
+}
+
+// Polls the health endpoint until the just-started daemon `pid` answers. A daemon that exits
+// first is reaped here rather than by monitor_daemon, so the watchdog doesn't restart a daemon
+// that can't even start; one that never answers is killed.
+async fn wait_until_ready(
+    app: &tauri::AppHandle,
+    state: &DaemonState,
+    pid: u32,
+    readiness: Readiness,
+) -> Result<(), AppError> {
+    let started = Instant::now();
+    let deadline = started + readiness.timeout;
+    loop {
+        let result = ping(gateway_port(state)).await;
+        emit_progress(
+            app,
+            ProgressEvent::Daemon(DaemonProgress {
+                pid,
+                elapsed_secs: started.elapsed().as_secs(),
+                timeout_secs: readiness.timeout.as_secs(),
+                ready: result.is_ok(),
+            }),
+        );
+        let last_error = match result {
+            Ok(_) => return Ok(()),
+            Err(e) => e,
+        };
+
+        {
+            let mut child_guard = state.0.lock().unwrap();
+            let exited = match child_guard.as_mut() {
+                Some(daemon) if daemon.child.id() == pid => daemon.child.try_wait().ok().flatten(),
+                _ => {
+                    return Err(AppError::DaemonNotRunning(
+                        "Daemon was stopped before it became ready".to_string(),
+                    ))
+                }
+            };
+            if let Some(status) = exited {
+                child_guard.take();
+                drop(child_guard);
+                remove_pid_file();
+                emit_daemon_exited(app, pid, Some(status), false);
+                return Err(AppError::DaemonNotRunning(format!(
+                    "Daemon exited ({}) before it became ready; see logs/daemon.log",
+                    status
+                )));
+            }
+        }
+
+        if Instant::now() >= deadline {
+            let daemon = {
+                let mut child_guard = state.0.lock().unwrap();
+                match child_guard.as_ref() {
+                    Some(daemon) if daemon.child.id() == pid => child_guard.take(),
+                    _ => None,
+                }
+            };
+            if let Some(mut daemon) = daemon {
+                let _ = daemon.child.kill();
+                let status = daemon.child.wait().ok();
+                remove_pid_file();
+                emit_daemon_exited(app, pid, status, true);
+            }
+            return Err(AppError::Timeout(format!(
+                "Daemon did not become ready within {}s and was stopped: {}",
+                readiness.timeout.as_secs(),
+                last_error
+            )));
+        }
+        tokio::time::sleep(readiness.poll_interval).await;
+    }
+}
+
+// The arguments to start the daemon with: `extra_args` if any were given, else `[daemon] args`
+fn daemon_args(
+    extra_args: Option<Vec<String>>,
+    profile: Option<&str>,
+) -> Result<Vec<String>, AppError> {
+    let args = match extra_args.filter(|args| !args.is_empty()) {
+        Some(args) => args,
+        None => read_profile_section::<DaemonConfig>("daemon", profile)?.args,
+    };
+    check_daemon_args(&args)?;
+    Ok(args)
+}
+
+// A flag given twice (`--port 3000 --port 3100`, or `--config=a --config b`) leaves it to the
+// daemon's parser which one wins, so refuse it instead
+fn check_daemon_args(args: &[String]) -> Result<(), AppError> {
+    let mut seen = HashSet::new();
+    for arg in args.iter().filter(|arg| arg.starts_with("--")) {
+        let flag = arg.split('=').next().unwrap_or(arg);
+        if !seen.insert(flag) {
+            return Err(AppError::InvalidInput(format!(
+                "Daemon flag '{}' was given more than once",
+                flag
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Where the wizard installs the bambooclaw binary: <bambooclaw dir>/bambooclaw(.exe)
+pub(crate) fn daemon_binary_path() -> Result<PathBuf, AppError> {
+    #[cfg(target_os = "windows")]
+    let bin_name = "bambooclaw.exe";
+    #[cfg(not(target_os = "windows"))]
+    let bin_name = "bambooclaw";
+
+    Ok(bambooclaw_dir()?.join(bin_name))
+}
+
+// Spawns `bambooclaw daemon` with `args`. Its stdout/stderr always end up in logs/daemon.log.
+// With `capture_logs` they are piped through the app, which also forwards each line as a
+// `daemon_log` event and rotates the log as it grows. Otherwise the daemon writes to the file
+// itself, so it keeps logging after the app exits; the log is then only rotated at startup.
+fn spawn_daemon(
+    app: &tauri::AppHandle,
+    capture_logs: bool,
+    args: Vec<String>,
+    profile: Option<String>,
+) -> Result<ManagedDaemon, AppError> {
+    let bin_path = daemon_binary_path()?;
+
+    let log_path = daemon_log_path()?;
+    if let Some(dir) = log_path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+
+    let mut cmd = std::process::Command::new(bin_path);
+    cmd.arg("daemon").args(&args).stdin(Stdio::null());
+    // The daemon reads config.toml from this directory instead of its default one
+    if let Some(name) = &profile {
+        cmd.env("BambooClawCore_WORKSPACE", profile_dir(name)?);
+    }
+    if capture_logs {
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    } else {
+        let log = open_daemon_log(&log_path);
+        let stderr_log = log.as_ref().and_then(|file| file.try_clone().ok());
+        cmd.stdout(log.map_or_else(Stdio::null, Stdio::from))
+            .stderr(stderr_log.map_or_else(Stdio::null, Stdio::from));
+    }
+
+    // Prevent the background agent from spawning its own window
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| AppError::io("Failed to start daemon", e))?;
+
+    if capture_logs {
+        let log = Arc::new(Mutex::new(DaemonLog::new(log_path)));
+        if let Some(stdout) = child.stdout.take() {
+            forward_output(app.clone(), stdout, "stdout", log.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_output(app.clone(), stderr, "stderr", log);
+        }
+    }
+
+    // Best effort: without the PID file a later session can't find this daemon, but it still runs
+    let _ = std::fs::write(pid_file_path()?, child.id().to_string());
+
+    Ok(ManagedDaemon {
+        child,
+        started_at: Instant::now(),
+        args,
+        profile,
+    })
+}
+
+// 9. Stop the background daemon. Returns once the process is gone and daemon.pid removed, so a
+// start_daemon right after can't find the old daemon still holding its port. A daemon that is
+// still there 5s after being killed is reported as Timeout and stays tracked.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip_all, err)]
+pub async fn stop_daemon(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, DaemonState>,
+) -> Result<String, AppError> {
+    let _lifecycle = LIFECYCLE_LOCK.lock().await;
+    let managed = state.0.lock().unwrap().take();
+
+    if let Some(mut daemon) = managed {
+        let pid = daemon.child.id();
+        let _ = daemon.child.kill();
+        let Some(status) = wait_for_child(&mut daemon.child, STOP_TIMEOUT).await else {
+            *state.0.lock().unwrap() = Some(daemon);
+            return Err(still_running(pid));
+        };
+        emit_daemon_exited(&app, pid, Some(status), true);
+    } else if let Some(pid) = recorded_daemon_pid() {
+        // Started by an earlier session of the app
+        let killed = {
+            let mut sys = System::new();
+            sys.refresh_process(Pid::from_u32(pid));
+            sys.process(Pid::from_u32(pid))
+                .is_some_and(|process| process.kill())
+        };
+        if killed {
+            if !wait_for_pid(pid, STOP_TIMEOUT).await {
+                return Err(still_running(pid));
+            }
+            emit_daemon_exited(&app, pid, None, true);
+        }
+    } else {
+        // Best effort for a daemon this session did not start. There is no reliable handle on it,
+        // so signal every process that is exactly the bambooclaw binary running `daemon`.
+        let signalled = signal_daemon_processes();
+        for &pid in &signalled {
+            if !wait_for_pid(pid, STOP_TIMEOUT).await {
+                return Err(still_running(pid));
+            }
+            emit_daemon_exited(&app, pid, None, true);
+        }
+        if !signalled.is_empty() {
+            remove_pid_file();
+            let pids: Vec<String> = signalled.iter().map(u32::to_string).collect();
+            return Ok(format!(
+                "Daemon stopped (signalled pid {})",
+                pids.join(", ")
+            ));
+        }
+    }
+
+    remove_pid_file();
+    Ok("Daemon stopped".to_string())
+}
+
+// Sends SIGTERM to every running daemon found by is_daemon_process (Windows has no SIGTERM, so
+// they are killed there) and returns the PIDs signalled
+fn signal_daemon_processes() -> Vec<u32> {
+    let mut sys = System::new();
+    sys.refresh_processes();
+    sys.processes()
+        .values()
+        .filter(|process| is_daemon_process(process))
+        .filter(|process| {
+            process
+                .kill_with(Signal::Term)
+                .unwrap_or_else(|| process.kill())
+        })
+        .map(|process| process.pid().as_u32())
+        .collect()
+}
+
+fn still_running(pid: u32) -> AppError {
+    AppError::Timeout(format!(
+        "Daemon (pid {}) was killed but had not exited after {}s",
+        pid,
+        STOP_TIMEOUT.as_secs()
+    ))
+}
+
+// 11. Report whether the daemon is alive, for the status indicator. A managed daemon that exited
+// on its own is reaped here and reported as stopped; without a managed daemon, the PID file from
+// an earlier session is consulted. Uptime is counted from when this session spawned the daemon,
+// or from the process start for one it adopted.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", skip_all, err)]
+pub fn daemon_status(
+    app: tauri::AppHandle,
+    state: tauri::State<DaemonState>,
+) -> Result<DaemonStatus, AppError> {
+    let mut child_guard = state.0.lock().unwrap();
+
+    let exited = match child_guard.as_mut() {
+        Some(daemon) => daemon
+            .child
+            .try_wait()
+            .map_err(|e| AppError::io("Failed to query daemon", e))?,
+        None => None,
+    };
+    if let Some(status) = exited {
+        let daemon = child_guard.take().unwrap();
+        remove_pid_file();
+        drop(child_guard);
+        // May bring up a replacement daemon, so look at the state again afterwards
+        handle_unexpected_exit(&app, daemon, status);
+        child_guard = state.0.lock().unwrap();
+    }
+
+    if let Some(daemon) = child_guard.as_ref() {
+        return Ok(DaemonStatus {
+            running: true,
+            pid: Some(daemon.child.id()),
+            uptime_secs: Some(daemon.started_at.elapsed().as_secs()),
+        });
+    }
+
+    let mut sys = System::new();
+    if let Some(pid) = recorded_daemon_pid() {
+        sys.refresh_process(Pid::from_u32(pid));
+        if let Some(process) = sys.process(Pid::from_u32(pid)) {
+            return Ok(DaemonStatus {
+                running: true,
+                pid: Some(pid),
+                uptime_secs: Some(process.run_time()),
+            });
+        }
+    }
+
+    Ok(DaemonStatus {
+        running: false,
+        pid: None,
+        uptime_secs: None,
+    })
+}
+
+// 33. Find a daemon left running by an earlier session of the app, so stop, status and restart
+// act on it and start_daemon doesn't launch a second one. Meant to be called at startup. The
+// daemon is taken from daemon.pid, or else found among running processes and recorded there; a
+// PID file pointing at a dead process is removed. Returns the daemon's PID, if there is one.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(state), err)]
+pub fn adopt_existing_daemon(state: tauri::State<DaemonState>) -> Result<Option<u32>, AppError> {
+    if let Some(daemon) = state.0.lock().unwrap().as_ref() {
+        return Ok(Some(daemon.child.id()));
+    }
+    if let Some(pid) = recorded_daemon_pid() {
+        return Ok(Some(pid));
+    }
+    let Some(pid) = find_daemon_process() else {
+        return Ok(None);
+    };
+    adopt(pid)?;
+    Ok(Some(pid))
+}
+
+// A running bambooclaw daemon, found by scanning processes. If several are running, the oldest
+// is the one earlier sessions were managing.
+fn find_daemon_process() -> Option<u32> {
+    let mut sys = System::new();
+    sys.refresh_processes();
+    sys.processes()
+        .values()
+        .filter(|process| is_daemon_process(process))
+        .min_by_key(|process| process.start_time())
+        .map(|process| process.pid().as_u32())
+}
+
+// Records `pid` in daemon.pid, so stop, status and restart act on it
+fn adopt(pid: u32) -> Result<(), AppError> {
+    std::fs::write(pid_file_path()?, pid.to_string())
+        .map_err(|e| AppError::io("Failed to write daemon.pid", e))?;
+    tracing::info!(pid, "adopted a running daemon");
+    Ok(())
+}
+
+// 12. Health-check the daemon over HTTP and return the round-trip latency in milliseconds.
+// Unlike daemon_status this catches a daemon whose process is alive but wedged.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", skip(state), err)]
+pub async fn ping_daemon(state: tauri::State<'_, DaemonState>) -> Result<u64, AppError> {
+    ping(gateway_port(&state)).await
+}
+
+async fn ping(port: u16) -> Result<u64, AppError> {
+    let url = format!("http://127.0.0.1:{}/health", port);
+
+    // Never route a loopback health check through a proxy
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .timeout(PING_TIMEOUT)
+        .build()
+        .map_err(|e| AppError::Other(format!("Failed to build HTTP client: {}", e)))?;
+
+    let started = Instant::now();
+    let response = client.get(&url).send().await.map_err(|e| {
+        if e.is_timeout() {
+            AppError::Timeout(format!(
+                "Daemon did not answer {} within {}s",
+                url,
+                PING_TIMEOUT.as_secs()
+            ))
+        } else if e.is_connect() {
+            AppError::DaemonNotRunning(format!("Daemon is not reachable at {}: {}", url, e))
+        } else {
+            AppError::NetworkError(format!("Daemon is not reachable at {}: {}", url, e))
+        }
+    })?;
+    if !response.status().is_success() {
+        return Err(AppError::NetworkError(format!(
+            "Daemon health check failed: HTTP {}",
+            response.status()
+        )));
+    }
+    Ok(started.elapsed().as_millis() as u64)
+}
+
+// 35. Report the CPU, memory and threads used by the daemon together with the processes it
+// spawned, such as the agent's Python workers. CPU is measured over a short sample, so the call
+// takes about 200ms; the UI can poll it to graph usage.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", skip(state), err)]
+pub async fn get_daemon_resource_usage(
+    state: tauri::State<'_, DaemonState>,
+) -> Result<ResourceUsage, AppError> {
+    let pid = current_daemon_pid(&state)
+        .ok_or_else(|| AppError::DaemonNotRunning("Daemon is not running".to_string()))?;
+    let pid = Pid::from_u32(pid);
+
+    // CPU usage is the difference between two refreshes
+    let mut sys = System::new();
+    sys.refresh_processes();
+    tokio::time::sleep(MINIMUM_CPU_UPDATE_INTERVAL).await;
+    sys.refresh_processes();
+
+    let daemon = sys
+        .process(pid)
+        .ok_or_else(|| AppError::DaemonNotRunning("Daemon is no longer running".to_string()))?;
+    let mut tree = descendants(&sys, pid);
+    tree.push(daemon);
+    Ok(ResourceUsage {
+        cpu_percent: tree.iter().map(|process| process.cpu_usage()).sum(),
+        memory_bytes: tree.iter().map(|process| process.memory()).sum(),
+        thread_count: tree
+            .iter()
+            .map(|process| process.tasks().map_or(1, |tasks| tasks.len().max(1)))
+            .sum(),
+    })
+}
+
+// The config profile of the daemon this session started; None for the default config.toml, and
+// for a daemon adopted from an earlier session
+pub(crate) fn running_profile(state: &DaemonState) -> Option<String> {
+    state
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|daemon| daemon.profile.clone())
+}
+
+// `[gateway] port` from the running daemon's config.toml, falling back to the daemon's default
+pub(crate) fn gateway_port(state: &DaemonState) -> u16 {
+    read_config(running_profile(state))
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .and_then(|config| config.get("gateway")?.get("port")?.as_integer())
+        .and_then(|port| u16::try_from(port).ok())
+        .unwrap_or(DEFAULT_GATEWAY_PORT)
+}
+
+// 13. Restart the daemon and return the new daemon's PID. The running daemon is first asked to
+// exit so it can flush its state, and is only force-killed once `graceful_timeout_secs` (default
+// 10s) has passed. The new daemon is only spawned once the old process is gone, and no other
+// start or stop can run in between.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(app, state, capture), err)]
+pub async fn restart_daemon(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, DaemonState>,
+    capture: tauri::State<'_, DaemonLogCapture>,
+    graceful_timeout_secs: Option<u64>,
+) -> Result<String, AppError> {
+    let _lifecycle = LIFECYCLE_LOCK.lock().await;
+    let _ = app.emit_all("daemon_restarting", ());
+
+    let timeout = graceful_timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_GRACEFUL_TIMEOUT);
+    // Keep the running daemon's arguments and profile; they may not be the configured ones
+    let running = state
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|d| (d.args.clone(), d.profile.clone()));
+    let (args, profile) = match running {
+        Some(running) => running,
+        None => (daemon_args(None, None)?, None),
+    };
+    let previous = current_daemon_pid(&state);
+    if !shutdown_any_daemon(&app, &state, timeout).await {
+        tracing::warn!(pid = previous, "the previous daemon had to be force-killed");
+    }
+    // Even a force-kill may not have taken effect yet
+    if let Some(previous) = previous {
+        if !wait_for_pid(previous, STOP_TIMEOUT).await {
+            return Err(still_running(previous));
+        }
+        remove_pid_file();
+    }
+
+    let daemon = spawn_daemon(&app, capture.0.load(Ordering::Relaxed), args, profile)?;
+    let pid = daemon.child.id();
+    *state.0.lock().unwrap() = Some(daemon);
+    monitor_daemon(app.clone(), pid);
+
+    let _ = app.emit_all("daemon_restarted", DaemonRestarted { pid });
+    Ok(pid.to_string())
+}
+
+// 34. Make the running daemon re-read config.toml without restarting it, so its PID and open
+// connections survive. On Unix the daemon is sent SIGHUP; Windows has no equivalent, so there
+// the daemon has to be restarted instead. Returns true once the daemon reports having applied
+// the new config, false if it didn't say so within 10s. A config the daemon can't load is
+// reported as InvalidConfig, and the daemon keeps running with its previous one.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(state), err)]
+pub async fn reload_daemon_config(state: tauri::State<'_, DaemonState>) -> Result<bool, AppError> {
+    let pid = current_daemon_pid(&state)
+        .ok_or_else(|| AppError::DaemonNotRunning("Daemon is not running".to_string()))?;
+    let port = gateway_port(&state);
+    let before = config_reload_state(port).await;
+    request_reload(pid).await?;
+
+    let deadline = Instant::now() + RELOAD_ACK_TIMEOUT;
+    while Instant::now() < deadline {
+        tokio::time::sleep(READY_POLL_INTERVAL).await;
+        let Some(reload) = config_reload_state(port).await else {
+            continue;
+        };
+        if before.as_ref() == Some(&reload) {
+            continue;
+        }
+        if let Some(error) = reload.last_error {
+            return Err(AppError::InvalidConfig(format!(
+                "Daemon kept its previous config: {}",
+                error
+            )));
+        }
+        tracing::info!(pid, "daemon reloaded its config");
+        return Ok(true);
+    }
+    tracing::warn!(pid, "daemon did not acknowledge the config reload");
+    Ok(false)
+}
+
+// The `config` component of the daemon's health report, which it updates after every reload
+#[derive(Deserialize, PartialEq)]
+struct ConfigReloadState {
+    updated_at: String,
+    last_error: Option<String>,
+}
+
+async fn config_reload_state(port: u16) -> Option<ConfigReloadState> {
+    let url = format!("http://127.0.0.1:{}/health", port);
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .timeout(PING_TIMEOUT)
+        .build()
+        .ok()?;
+    let body = client.get(&url).send().await.ok()?.text().await.ok()?;
+    let health: serde_json::Value = serde_json::from_str(&body).ok()?;
+    serde_json::from_value(health["runtime"]["components"]["config"].clone()).ok()
+}
+
+#[cfg(not(target_os = "windows"))]
+async fn request_reload(pid: u32) -> Result<(), AppError> {
+    run_checked(
+        "kill",
+        &["-HUP".to_string(), pid.to_string()],
+        &ShellOptions::default(),
+    )
+    .await
+    .map(|_| ())
+}
+
+#[cfg(target_os = "windows")]
+async fn request_reload(_pid: u32) -> Result<(), AppError> {
+    Err(AppError::Other(
+        "The daemon can't reload its config in place on Windows; restart it instead".to_string(),
+    ))
+}
+
+// Gracefully stops the managed daemon, or else one recorded by an earlier session.
+// Returns false only if the daemon had to be force-killed.
+pub(crate) async fn shutdown_any_daemon(
+    app: &tauri::AppHandle,
+    state: &DaemonState,
+    timeout: Duration,
+) -> bool {
+    let previous = state.0.lock().unwrap().take();
+    if let Some(mut daemon) = previous {
+        let graceful = shutdown_daemon(&mut daemon.child, timeout).await;
+        // Already reaped, so this just returns the recorded status
+        let status = daemon.child.try_wait().ok().flatten();
+        emit_daemon_exited(app, daemon.child.id(), status, true);
+        graceful
+    } else if let Some(pid) = recorded_daemon_pid() {
+        let graceful = shutdown_process(pid, timeout).await;
+        emit_daemon_exited(app, pid, None, true);
+        graceful
+    } else {
+        true
+    }
+}
+
+// Asks the daemon to exit and waits up to `timeout` for it to do so, then force-kills it.
+// Returns true if the daemon exited on its own.
+async fn shutdown_daemon(child: &mut Child, timeout: Duration) -> bool {
+    request_exit(child.id()).await;
+    if wait_for_child(child, timeout).await.is_some() {
+        return true;
+    }
+
+    let _ = child.kill();
+    wait_for_child(child, STOP_TIMEOUT).await;
+    false
+}
+
+// Reaps `child` once it exits; None if it is still running after `timeout`
+async fn wait_for_child(child: &mut Child, timeout: Duration) -> Option<ExitStatus> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Ok(Some(status)) = child.try_wait() {
+            return Some(status);
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+        tokio::time::sleep(EXIT_POLL_INTERVAL).await;
+    }
+}
+
+// Like wait_for_child, for a process that isn't ours to reap. True once `pid` is gone.
+async fn wait_for_pid(pid: u32, timeout: Duration) -> bool {
+    let pid = Pid::from_u32(pid);
+    let mut sys = System::new();
+    let deadline = Instant::now() + timeout;
+    loop {
+        if !sys.refresh_process(pid) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(EXIT_POLL_INTERVAL).await;
+    }
+}
+
+// Sends the OS termination request: SIGTERM on Unix, a non-forced taskkill on Windows
+pub(crate) async fn request_exit(pid: u32) {
+    #[cfg(target_os = "windows")]
+    let _ = run_checked(
+        "taskkill",
+        &["/PID".to_string(), pid.to_string()],
+        &ShellOptions::default(),
+    )
+    .await;
+    #[cfg(not(target_os = "windows"))]
+    let _ = run_checked(
+        "kill",
+        &["-TERM".to_string(), pid.to_string()],
+        &ShellOptions::default(),
+    )
+    .await;
+}
+
+// Like shutdown_daemon, for a process known only by its PID, such as a daemon an earlier session
+// recorded
+pub(crate) async fn shutdown_process(pid: u32, timeout: Duration) -> bool {
+    request_exit(pid).await;
+    if wait_for_pid(pid, timeout).await {
+        return true;
+    }
+
+    let mut sys = System::new();
+    sys.refresh_process(Pid::from_u32(pid));
+    if let Some(process) = sys.process(Pid::from_u32(pid)) {
+        process.kill();
+    }
+    wait_for_pid(pid, STOP_TIMEOUT).await;
+    false
+}
+
+// Runs once as the app exits, on the main thread. With `[daemon] stop_daemon_on_exit` in the
+// running daemon's config, the daemon is asked to exit and killed if it hasn't within
+// EXIT_SHUTDOWN_TIMEOUT, so quitting takes at most that plus STOP_TIMEOUT. Otherwise it is left
+// running and daemon.pid is written again, so the next session can adopt it.
+pub(crate) fn on_app_exit(app: &tauri::AppHandle) {
+    let state = app.state::<DaemonState>();
+    let config = read_profile_section::<DaemonConfig>("daemon", running_profile(&state).as_deref())
+        .unwrap_or_default();
+    if config.stop_daemon_on_exit {
+        tauri::async_runtime::block_on(shutdown_any_daemon(app, &state, EXIT_SHUTDOWN_TIMEOUT));
+        remove_pid_file();
+        return;
+    }
+
+    let pid = state
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|daemon| daemon.child.id());
+    if let (Some(pid), Ok(path)) = (pid, pid_file_path()) {
+        if let Err(e) = std::fs::write(&path, pid.to_string()) {
+            tracing::warn!(pid, "failed to write {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn pid_file_path() -> Result<PathBuf, AppError> {
+    Ok(bambooclaw_dir()?.join("daemon.pid"))
+}
+
+pub(crate) fn remove_pid_file() {
+    if let Ok(path) = pid_file_path() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+// PID of the daemon this session started, or else of one recorded by an earlier session
+pub(crate) fn current_daemon_pid(state: &DaemonState) -> Option<u32> {
+    let tracked = state
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|daemon| daemon.child.id());
+    tracked.or_else(recorded_daemon_pid)
+}
+
+// The PID recorded in daemon.pid, provided it still belongs to a running bambooclaw daemon.
+// PIDs get recycled, so a stale file pointing at some other process is removed instead.
+fn recorded_daemon_pid() -> Option<u32> {
+    let path = pid_file_path().ok()?;
+    let pid: u32 = std::fs::read_to_string(&path).ok()?.trim().parse().ok()?;
+
+    let mut sys = System::new();
+    sys.refresh_process(Pid::from_u32(pid));
+    match sys.process(Pid::from_u32(pid)) {
+        Some(process) if is_daemon_process(process) => Some(pid),
+        _ => {
+            let _ = std::fs::remove_file(&path);
+            None
+        }
+    }
+}
+
+// A bambooclaw executable whose first argument is the `daemon` subcommand, other than this app.
+// The name alone is not enough: on Windows the app itself is also called bambooclaw.exe. Scripts
+// or editors that merely mention "bambooclaw daemon" in their arguments don't match either.
+fn is_daemon_process(process: &Process) -> bool {
+    #[cfg(target_os = "windows")]
+    let is_binary = process
+        .name()
+        .strip_suffix(".exe")
+        .is_some_and(|name| name.eq_ignore_ascii_case("bambooclaw"));
+    #[cfg(not(target_os = "windows"))]
+    let is_binary = process.name() == "bambooclaw";
+    is_binary
+        && process.cmd().get(1).is_some_and(|arg| arg == "daemon")
+        && process.pid().as_u32() != std::process::id()
+}
+
+// 14. Toggle streaming of daemon stdout/stderr to the UI as `daemon_log` events. The output is
+// written to logs/daemon.log either way. Takes effect the next time the daemon starts.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(capture), err)]
+pub fn set_daemon_log_capture(
+    capture: tauri::State<DaemonLogCapture>,
+    enabled: bool,
+) -> Result<String, AppError> {
+    capture.0.store(enabled, Ordering::Relaxed);
+    Ok(format!(
+        "Daemon log capture {}; applies from the next daemon start",
+        if enabled { "enabled" } else { "disabled" }
+    ))
+}
+
+// Reads one of the daemon's output pipes line by line on a background thread until it closes
+fn forward_output<R: Read + Send + 'static>(
+    app: tauri::AppHandle,
+    reader: R,
+    stream: &'static str,
+    log: Arc<Mutex<DaemonLog>>,
+) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else { break };
+            log.lock().unwrap().append(stream, &line);
+            let _ = app.emit_all("daemon_log", DaemonLogLine { stream, line });
+        }
+    });
+}
+
+// <bambooclaw dir>/logs/daemon.log
+pub(crate) fn daemon_log_path() -> Result<PathBuf, AppError> {
+    Ok(bambooclaw_dir()?.join("logs").join("daemon.log"))
+}
+
+// daemon.log.<n>, the n-th most recent rotated log
+pub(crate) fn rotated_daemon_log_path(path: &Path, n: usize) -> PathBuf {
+    path.with_extension(format!("log.{}", n))
+}
+
+// daemon.log and its rotated copies, oldest first, whether they exist or not
+pub(crate) fn daemon_log_files() -> Result<Vec<PathBuf>, AppError> {
+    let path = daemon_log_path()?;
+    let mut files: Vec<PathBuf> = (1..=DAEMON_LOG_KEEP)
+        .rev()
+        .map(|n| rotated_daemon_log_path(&path, n))
+        .collect();
+    files.push(path);
+    Ok(files)
+}
+
+// Opens daemon.log for appending, rotating it first if it has outgrown DAEMON_LOG_MAX_BYTES
+fn open_daemon_log(path: &Path) -> Option<File> {
+    if std::fs::metadata(path).is_ok_and(|m| m.len() > DAEMON_LOG_MAX_BYTES) {
+        rotate_daemon_logs(path);
+    }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .ok()
+}
+
+fn rotate_daemon_logs(path: &Path) {
+    for n in (1..DAEMON_LOG_KEEP).rev() {
+        let _ = std::fs::rename(
+            rotated_daemon_log_path(path, n),
+            rotated_daemon_log_path(path, n + 1),
+        );
+    }
+    let _ = std::fs::rename(path, rotated_daemon_log_path(path, 1));
+}
+
+// Append-only daemon.log, rotated as it grows
+struct DaemonLog {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl DaemonLog {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: None,
+            size: 0,
+        }
+    }
+
+    // Logging is best effort: write failures never disturb the daemon
+    fn append(&mut self, stream: &str, line: &str) {
+        if self.size > DAEMON_LOG_MAX_BYTES {
+            self.file = None;
+            rotate_daemon_logs(&self.path);
+        }
+        if self.file.is_none() {
+            self.file = open_daemon_log(&self.path);
+            self.size = std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        }
+        if let Some(file) = self.file.as_mut() {
+            let entry = format!("[{}] {}\n", stream, line);
+            if file.write_all(entry.as_bytes()).is_ok() {
+                self.size += entry.len() as u64;
+            }
+        }
+    }
+}
+
+// 15. Configure the watchdog that restarts the daemon after it crashes
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(watchdog), err)]
+pub fn enable_daemon_watchdog(
+    watchdog: tauri::State<WatchdogState>,
+    policy: WatchdogPolicy,
+) -> Result<String, AppError> {
+    let message = if policy.enabled {
+        format!(
+            "Daemon watchdog enabled: up to {} restarts per {}s",
+            policy.max_restarts, policy.window_secs
+        )
+    } else {
+        "Daemon watchdog disabled".to_string()
+    };
+    let mut watchdog = watchdog.0.lock().unwrap();
+    watchdog.policy = policy;
+    watchdog.restarts.clear();
+    Ok(message)
+}
+
+// Watches the managed daemon with `pid` in the background until it exits. Deliberate stops
+// take the daemon out of DaemonState (or replace it), which ends the watch without any action.
+fn monitor_daemon(app: tauri::AppHandle, pid: u32) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(MONITOR_INTERVAL).await;
+
+            let state = app.state::<DaemonState>();
+            let mut child_guard = state.0.lock().unwrap();
+            let status = match child_guard.as_mut() {
+                Some(daemon) if daemon.child.id() == pid => match daemon.child.try_wait() {
+                    Ok(Some(status)) => status,
+                    Ok(None) => continue,
+                    Err(_) => return,
+                },
+                _ => return,
+            };
+            let daemon = child_guard.take().unwrap();
+            drop(child_guard);
+
+            remove_pid_file();
+            handle_unexpected_exit(&app, daemon, status);
+            return;
+        }
+    });
+}
+
+fn emit_daemon_exited(
+    app: &tauri::AppHandle,
+    pid: u32,
+    status: Option<ExitStatus>,
+    expected: bool,
+) {
+    #[cfg(unix)]
+    let signal = status.and_then(|status| {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal()
+    });
+    #[cfg(not(unix))]
+    let signal = None;
+    let _ = app.emit_all(
+        "daemon_exited",
+        DaemonExited {
+            pid,
+            exit_code: status.and_then(|status| status.code()),
+            signal,
+            expected,
+        },
+    );
+}
+
+// Reacts to the managed daemon exiting without being asked to. Failures are reported with
+// `daemon_crashed` and, if the watchdog allows it, answered with a fresh daemon.
+fn handle_unexpected_exit(app: &tauri::AppHandle, daemon: ManagedDaemon, status: ExitStatus) {
+    let pid = daemon.child.id();
+    emit_daemon_exited(app, pid, Some(status), false);
+    if status.success() {
+        tracing::info!(pid, "daemon exited on its own");
+        return;
+    }
+    tracing::warn!(pid, exit_code = ?status.code(), "daemon crashed");
+    let _ = app.emit_all(
+        "daemon_crashed",
+        DaemonCrashed {
+            pid,
+            exit_code: status.code(),
+        },
+    );
+
+    let restarts_in_window = {
+        let watchdog_state = app.state::<WatchdogState>();
+        let mut watchdog = watchdog_state.0.lock().unwrap();
+        if !watchdog.policy.enabled {
+            return;
+        }
+        let window = Duration::from_secs(watchdog.policy.window_secs);
+        while watchdog
+            .restarts
+            .front()
+            .is_some_and(|at| at.elapsed() > window)
+        {
+            watchdog.restarts.pop_front();
+        }
+        if watchdog.restarts.len() >= watchdog.policy.max_restarts as usize {
+            return;
+        }
+        watchdog.restarts.push_back(Instant::now());
+        watchdog.restarts.len()
+    };
+
+    let state = app.state::<DaemonState>();
+    let mut child_guard = state.0.lock().unwrap();
+    if child_guard.is_some() {
+        // Someone started a new daemon in the meantime
+        return;
+    }
+    let capture = app.state::<DaemonLogCapture>().0.load(Ordering::Relaxed);
+    match spawn_daemon(app, capture, daemon.args, daemon.profile) {
+        Ok(daemon) => {
+            let new_pid = daemon.child.id();
+            *child_guard = Some(daemon);
+            drop(child_guard);
+            monitor_daemon(app.clone(), new_pid);
+            tracing::info!(
+                pid = new_pid,
+                restarts_in_window,
+                "watchdog restarted the daemon"
+            );
+            let _ = app.emit_all(
+                "daemon_auto_restarted",
+                DaemonAutoRestarted {
+                    pid: new_pid,
+                    restarts_in_window,
+                },
+            );
+        }
+        Err(e) => tracing::error!(error = %e, "watchdog failed to restart the daemon"),
+    }
+}
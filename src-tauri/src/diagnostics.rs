@@ -0,0 +1,106 @@
+use crate::config::redacted_config;
+use crate::daemon::{current_daemon_pid, daemon_log_path, rotated_daemon_log_path, DaemonState};
+use crate::error::AppError;
+use crate::flush::bambooclaw_processes;
+use crate::logging::installer_log_files;
+use crate::{bambooclaw_dir, get_system_info};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+// Only the end of a log larger than this goes into the archive
+const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+
+// 30. Bundle everything a bug report needs into one zip in the bambooclaw dir's diagnostics and
+// return its path: the daemon log and its previous rotation, the app's own logs, config.toml
+// with secrets redacted, the bambooclaw processes running, and the app version and OS.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(state), err)]
+pub async fn export_diagnostics(state: tauri::State<'_, DaemonState>) -> Result<String, AppError> {
+    let daemon_pid = current_daemon_pid(&state);
+    tauri::async_runtime::spawn_blocking(move || write_archive(daemon_pid))
+        .await
+        .map_err(|e| AppError::Other(format!("Task join error: {}", e)))?
+}
+
+fn write_archive(daemon_pid: Option<u32>) -> Result<String, AppError> {
+    let dir = bambooclaw_dir()?.join("diagnostics");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AppError::io(format!("Failed to create {}", dir.display()), e))?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = dir.join(format!("bambooclaw-diagnostics-{}.zip", timestamp));
+    let file = File::create(&path)
+        .map_err(|e| AppError::io(format!("Failed to create {}", path.display()), e))?;
+    let mut zip = ZipWriter::new(file);
+
+    let system = serde_json::json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "system": get_system_info(),
+    });
+    add_text(
+        &mut zip,
+        "system.json",
+        &serde_json::to_string_pretty(&system).unwrap_or_default(),
+    )?;
+    add_text(
+        &mut zip,
+        "processes.json",
+        &serde_json::to_string_pretty(&bambooclaw_processes(daemon_pid)).unwrap_or_default(),
+    )?;
+    // A config that can't be parsed can't be redacted either, so it is left out
+    let config = redacted_config(None)
+        .unwrap_or_else(|e| format!("# config.toml could not be included: {}\n", e));
+    add_text(&mut zip, "config.toml", &config)?;
+
+    let daemon_log = daemon_log_path()?;
+    let mut logs: Vec<PathBuf> = vec![rotated_daemon_log_path(&daemon_log, 1), daemon_log];
+    logs.extend(installer_log_files()?);
+    for log in &logs {
+        add_log(&mut zip, log)?;
+    }
+
+    zip.finish().map_err(archive_error)?;
+    Ok(path.display().to_string())
+}
+
+fn add_text(zip: &mut ZipWriter<File>, name: &str, content: &str) -> Result<(), AppError> {
+    zip.start_file(name, SimpleFileOptions::default())
+        .map_err(archive_error)?;
+    zip.write_all(content.as_bytes()).map_err(archive_error)
+}
+
+// Adds `path` as logs/<file name>, keeping only its last MAX_LOG_BYTES. Missing logs are skipped.
+fn add_log(zip: &mut ZipWriter<File>, path: &Path) -> Result<(), AppError> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            return Err(AppError::io(
+                format!("Failed to read {}", path.display()),
+                e,
+            ))
+        }
+    };
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    if len > MAX_LOG_BYTES {
+        file.seek(SeekFrom::Start(len - MAX_LOG_BYTES))
+            .map_err(|e| AppError::io(format!("Failed to read {}", path.display()), e))?;
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    zip.start_file(format!("logs/{}", name), SimpleFileOptions::default())
+        .map_err(archive_error)?;
+    // The daemon may still be writing; stop at the size measured above
+    std::io::copy(&mut file.take(MAX_LOG_BYTES), zip)
+        .map_err(|e| AppError::io(format!("Failed to read {}", path.display()), e))?;
+    Ok(())
+}
+
+fn archive_error(e: impl std::fmt::Display) -> AppError {
+    AppError::IoError(format!("Failed to write the diagnostics archive: {}", e))
+}
@@ -0,0 +1,111 @@
+use serde::Serialize;
+use std::fmt;
+
+// Error returned by every command. It reaches the frontend as `{ "code": "not_found", "message":
+// "..." }`: `code` is stable and meant for branching, `message` is for people and may change.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "code", content = "message", rename_all = "snake_case")]
+pub enum AppError {
+    // A file, directory, program, release or other named thing doesn't exist
+    NotFound(String),
+    Timeout(String),
+    // A request failed to get an answer: DNS, refused connections, HTTP errors
+    NetworkError(String),
+    // Reading or writing local files failed for a reason other than a missing path
+    IoError(String),
+    // The frontend asked for something malformed or out of range
+    InvalidInput(String),
+    // config.toml exists but can't be parsed, or a key in it has the wrong shape
+    InvalidConfig(String),
+    // The command is refused by the `[shell] allowed_commands` allowlist, the process to kill
+    // doesn't belong to bambooclaw, or an emergency flush token is unknown or expired
+    NotAllowed(String),
+    // A command ran but exited with a failure status; the message includes its stderr
+    CommandFailed(String),
+    // An installed tool is older than the minimum version asked for
+    VersionTooOld(String),
+    InsufficientDiskSpace(String),
+    DaemonAlreadyRunning(String),
+    DaemonNotRunning(String),
+    ChecksumMismatch(String),
+    // A download's signature is malformed, from another key, or doesn't match the file
+    SignatureInvalid(String),
+    // A downloaded file isn't a program for this platform, e.g. an HTML error page, or doesn't run
+    NotExecutable(String),
+    Cancelled(String),
+    // A download grew past the `max_bytes` it was allowed
+    TooLarge(String),
+    // GitHub's API quota is spent; the message says when it refills
+    RateLimited(String),
+    Other(String),
+}
+
+impl AppError {
+    // A failed filesystem operation, described as `context: error`. Missing paths map to
+    // NotFound so the frontend can tell them apart from permission or disk problems.
+    pub fn io(context: impl fmt::Display, error: std::io::Error) -> Self {
+        let message = format!("{}: {}", context, error);
+        match error.kind() {
+            std::io::ErrorKind::NotFound => AppError::NotFound(message),
+            std::io::ErrorKind::TimedOut => AppError::Timeout(message),
+            _ => AppError::IoError(message),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::NotFound(message)
+            | AppError::Timeout(message)
+            | AppError::NetworkError(message)
+            | AppError::IoError(message)
+            | AppError::InvalidInput(message)
+            | AppError::InvalidConfig(message)
+            | AppError::NotAllowed(message)
+            | AppError::CommandFailed(message)
+            | AppError::VersionTooOld(message)
+            | AppError::InsufficientDiskSpace(message)
+            | AppError::DaemonAlreadyRunning(message)
+            | AppError::DaemonNotRunning(message)
+            | AppError::ChecksumMismatch(message)
+            | AppError::SignatureInvalid(message)
+            | AppError::NotExecutable(message)
+            | AppError::Cancelled(message)
+            | AppError::TooLarge(message)
+            | AppError::RateLimited(message)
+            | AppError::Other(message) => message,
+        }
+    }
+
+    // Rewrites the message, keeping the kind
+    pub fn map_message(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            AppError::NotFound(message) => AppError::NotFound(f(message)),
+            AppError::Timeout(message) => AppError::Timeout(f(message)),
+            AppError::NetworkError(message) => AppError::NetworkError(f(message)),
+            AppError::IoError(message) => AppError::IoError(f(message)),
+            AppError::InvalidInput(message) => AppError::InvalidInput(f(message)),
+            AppError::InvalidConfig(message) => AppError::InvalidConfig(f(message)),
+            AppError::NotAllowed(message) => AppError::NotAllowed(f(message)),
+            AppError::CommandFailed(message) => AppError::CommandFailed(f(message)),
+            AppError::VersionTooOld(message) => AppError::VersionTooOld(f(message)),
+            AppError::InsufficientDiskSpace(message) => AppError::InsufficientDiskSpace(f(message)),
+            AppError::DaemonAlreadyRunning(message) => AppError::DaemonAlreadyRunning(f(message)),
+            AppError::DaemonNotRunning(message) => AppError::DaemonNotRunning(f(message)),
+            AppError::ChecksumMismatch(message) => AppError::ChecksumMismatch(f(message)),
+            AppError::SignatureInvalid(message) => AppError::SignatureInvalid(f(message)),
+            AppError::NotExecutable(message) => AppError::NotExecutable(f(message)),
+            AppError::Cancelled(message) => AppError::Cancelled(f(message)),
+            AppError::TooLarge(message) => AppError::TooLarge(f(message)),
+            AppError::RateLimited(message) => AppError::RateLimited(f(message)),
+            AppError::Other(message) => AppError::Other(f(message)),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
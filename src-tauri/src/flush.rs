@@ -0,0 +1,390 @@
+use crate::bambooclaw_dir;
+use crate::config::{agent_temp_dir, read_section};
+use crate::daemon::{self, DaemonState};
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, Process, System, MINIMUM_CPU_UPDATE_INTERVAL};
+
+// How long kill_process with `force` gives a process to exit before killing it
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(3);
+// How long a token from request_emergency_flush can confirm the flush
+const FLUSH_TOKEN_TTL: Duration = Duration::from_secs(30);
+
+// Tokens handed out by request_emergency_flush that haven't been used yet, with when each was
+// issued
+#[derive(Default)]
+pub struct FlushTokens(Mutex<HashMap<String, Instant>>);
+
+// `[emergency]` in config.toml
+#[derive(Deserialize)]
+#[serde(default)]
+struct FlushConfig {
+    // Processes to kill by executable name (`.exe` is optional) when no daemon is known, and then
+    // only those whose command line points into the agent's temp dir or the bambooclaw dir
+    process_names: Vec<String>,
+    // What to delete from the agent's temp dir, as glob patterns relative to it
+    temp_patterns: Vec<String>,
+}
+
+impl Default for FlushConfig {
+    fn default() -> Self {
+        FlushConfig {
+            process_names: Vec::new(),
+            temp_patterns: vec!["*".to_string()],
+        }
+    }
+}
+
+// What an emergency flush killed and removed, or with `dry_run`, what it would
+#[derive(Serialize)]
+pub struct FlushReport {
+    dry_run: bool,
+    processes: Vec<FlushedProcess>,
+    temp_files: Vec<String>,
+}
+
+// Reply of request_emergency_flush: the plan, and the token that confirms it
+#[derive(Serialize)]
+pub struct FlushRequest {
+    token: String,
+    expires_in_secs: u64,
+    plan: FlushReport,
+}
+
+#[derive(Serialize)]
+pub struct FlushedProcess {
+    pid: u32,
+    name: String,
+}
+
+// One entry of list_bambooclaw_processes
+#[derive(Clone, Serialize)]
+pub struct ProcessInfo {
+    pid: u32,
+    name: String,
+    command_line: String,
+    parent_pid: Option<u32>,
+    // Share of one core over a short sample
+    cpu_percent: f32,
+    memory_bytes: u64,
+}
+
+// 10. Emergency Flush, step one — list what a flush would kill and delete, for a confirmation
+// prompt, along with a one-time token. Nothing is touched until confirm_emergency_flush is called
+// with that token, so a single stray call can't wipe anything.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(app, state, tokens), err)]
+pub async fn request_emergency_flush(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, DaemonState>,
+    tokens: tauri::State<'_, FlushTokens>,
+) -> Result<FlushRequest, AppError> {
+    let plan = flush(app, state, true).await?;
+    let token = format!("{:032x}", rand::random::<u128>());
+    let mut pending = tokens.0.lock().unwrap();
+    pending.retain(|_, issued| issued.elapsed() < FLUSH_TOKEN_TTL);
+    pending.insert(token.clone(), Instant::now());
+    Ok(FlushRequest {
+        token,
+        expires_in_secs: FLUSH_TOKEN_TTL.as_secs(),
+        plan,
+    })
+}
+
+// 10b. Emergency Flush, step two — stop the daemon and every process it started, directly or not,
+// then clear the agent's temp dir. Without a known daemon, processes named in `[emergency]
+// process_names` that work on the agent's files are stopped instead. `token` must come from
+// request_emergency_flush within the last 30s and works once; otherwise the call is NotAllowed.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(app, state, tokens, token), err)]
+pub async fn confirm_emergency_flush(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, DaemonState>,
+    tokens: tauri::State<'_, FlushTokens>,
+    token: String,
+) -> Result<FlushReport, AppError> {
+    let issued = tokens.0.lock().unwrap().remove(&token);
+    let valid = matches!(issued, Some(issued) if issued.elapsed() < FLUSH_TOKEN_TTL);
+    if !valid {
+        return Err(AppError::NotAllowed(
+            "The emergency flush token is invalid or has expired; request a new one".to_string(),
+        ));
+    }
+    flush(app, state, false).await
+}
+
+// With `dry_run`, nothing is touched and the report lists what a real run would do
+async fn flush(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, DaemonState>,
+    dry_run: bool,
+) -> Result<FlushReport, AppError> {
+    // The kill switch has to work even when config.toml is broken
+    let config: FlushConfig = read_section("emergency").unwrap_or_default();
+
+    let mut sys = System::new();
+    sys.refresh_processes();
+    // Never this app, whatever the config says: on Windows it is bambooclaw.exe too
+    let own_pid = Pid::from_u32(std::process::id());
+    let daemon_pid = daemon::current_daemon_pid(state.inner()).map(Pid::from_u32);
+
+    let targets = match daemon_pid {
+        Some(daemon_pid) => descendants(&sys, daemon_pid),
+        None => {
+            let scope = agent_paths();
+            processes(&sys)
+                .filter(|process| process.pid() != own_pid)
+                .filter(|process| matches_name(process, &config.process_names))
+                .filter(|process| references_any(process, &scope))
+                .collect()
+        }
+    };
+    let mut processes: Vec<FlushedProcess> = targets
+        .into_iter()
+        .filter(|process| dry_run || process.kill())
+        .map(flushed)
+        .collect();
+
+    // The daemon itself goes last, so it can't restart what was just killed
+    if let Some(process) = daemon_pid.and_then(|pid| sys.process(pid)) {
+        processes.push(flushed(process));
+    }
+    if !dry_run {
+        daemon::stop_daemon(app, state).await?;
+    }
+
+    let mut temp_files = Vec::new();
+    for path in matching_temp_files(&agent_temp_dir(), &config.temp_patterns) {
+        if dry_run || remove_entry(&path).is_ok() {
+            temp_files.push(path.display().to_string());
+        }
+    }
+
+    Ok(FlushReport {
+        dry_run,
+        processes,
+        temp_files,
+    })
+}
+
+// 36. List every process that belongs to bambooclaw: the daemon and everything below it, any
+// program named after bambooclaw, and helpers such as the agent's Python workers that work in its
+// temp dir. This app itself is left out. CPU is sampled, so the
+// call takes about 200ms.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", skip(state), err)]
+pub async fn list_bambooclaw_processes(
+    state: tauri::State<'_, DaemonState>,
+) -> Result<Vec<ProcessInfo>, AppError> {
+    let daemon_pid = daemon::current_daemon_pid(state.inner());
+    tauri::async_runtime::spawn_blocking(move || bambooclaw_processes(daemon_pid))
+        .await
+        .map_err(|e| AppError::Other(format!("Task join error: {}", e)))
+}
+
+// Blocks for the CPU sample; see list_bambooclaw_processes
+pub(crate) fn bambooclaw_processes(daemon_pid: Option<u32>) -> Vec<ProcessInfo> {
+    let mut sys = System::new();
+    sys.refresh_processes();
+    std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+    sys.refresh_processes();
+
+    let mut found: Vec<ProcessInfo> = related_processes(&sys, daemon_pid)
+        .into_iter()
+        .map(|process| ProcessInfo {
+            pid: process.pid().as_u32(),
+            name: process.name().to_string(),
+            command_line: process.cmd().join(" "),
+            parent_pid: process.parent().map(|pid| pid.as_u32()),
+            cpu_percent: process.cpu_usage(),
+            memory_bytes: process.memory(),
+        })
+        .collect();
+    found.sort_by_key(|process| process.pid);
+    found
+}
+
+// 37. Stop one process from list_bambooclaw_processes, leaving the rest running. It is asked to
+// exit (SIGTERM, or a plain taskkill on Windows); with `force` it is killed if it hasn't exited
+// after 3s. A PID that isn't a bambooclaw process is refused with NotAllowed, and the daemon
+// itself has to be stopped with stop_daemon.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(state), err)]
+pub async fn kill_process(
+    state: tauri::State<'_, DaemonState>,
+    pid: u32,
+    force: bool,
+) -> Result<String, AppError> {
+    let daemon_pid = daemon::current_daemon_pid(state.inner());
+    if daemon_pid == Some(pid) {
+        return Err(AppError::InvalidInput(format!(
+            "Process {} is the daemon; use stop_daemon to stop it",
+            pid
+        )));
+    }
+    let related = {
+        let mut sys = System::new();
+        sys.refresh_processes();
+        related_processes(&sys, daemon_pid)
+            .iter()
+            .any(|process| process.pid().as_u32() == pid)
+    };
+    if !related {
+        return Err(AppError::NotAllowed(format!(
+            "Process {} is not a bambooclaw process",
+            pid
+        )));
+    }
+
+    if !force {
+        daemon::request_exit(pid).await;
+        return Ok(format!("Asked process {} to exit", pid));
+    }
+    if daemon::shutdown_process(pid, KILL_GRACE_PERIOD).await {
+        Ok(format!("Process {} exited", pid))
+    } else {
+        Ok(format!("Process {} was force-killed", pid))
+    }
+}
+
+// The processes list_bambooclaw_processes reports, never including this app
+fn related_processes(sys: &System, daemon_pid: Option<u32>) -> Vec<&Process> {
+    let own_pid = Pid::from_u32(std::process::id());
+    let mut daemon_tree: Vec<Pid> = Vec::new();
+    if let Some(pid) = daemon_pid.map(Pid::from_u32) {
+        daemon_tree.push(pid);
+        daemon_tree.extend(descendants(sys, pid).iter().map(|process| process.pid()));
+    }
+    let temp_dir = [agent_temp_dir().display().to_string()];
+
+    processes(sys)
+        .filter(|process| process.pid() != own_pid)
+        .filter(|process| {
+            daemon_tree.contains(&process.pid())
+                || runs_bambooclaw(process)
+                || references_any(process, &temp_dir)
+        })
+        .collect()
+}
+
+// Whether the program itself is named after bambooclaw. Only the program is looked at, not its
+// arguments, so an editor that has config.toml open isn't mistaken for part of the agent.
+fn runs_bambooclaw(process: &Process) -> bool {
+    let program = process
+        .cmd()
+        .first()
+        .and_then(|arg| Path::new(arg).file_name())
+        .map(|name| name.to_string_lossy());
+    std::iter::once(process.name().into())
+        .chain(program)
+        .any(|name| name.to_lowercase().contains("bambooclaw"))
+}
+
+// sysinfo also lists each thread as a process on Linux; those are left out
+fn processes(sys: &System) -> impl Iterator<Item = &Process> {
+    sys.processes()
+        .values()
+        .filter(|process| process.thread_kind().is_none())
+}
+
+// Every process below `root` in the process tree, deepest first, so nothing is left running long
+// enough to spawn a replacement for a child that was just killed
+pub(crate) fn descendants(sys: &System, root: Pid) -> Vec<&Process> {
+    let mut found: Vec<&Process> = Vec::new();
+    let mut parents = vec![root];
+    while let Some(parent) = parents.pop() {
+        for process in processes(sys) {
+            // The found check guards against PID reuse creating a cycle
+            if process.parent() == Some(parent) && !found.iter().any(|p| p.pid() == process.pid()) {
+                found.push(process);
+                parents.push(process.pid());
+            }
+        }
+    }
+    found.reverse();
+    found
+}
+
+// Paths whose appearance on a command line marks a process as working for the agent
+fn agent_paths() -> Vec<String> {
+    let mut paths = vec![agent_temp_dir().display().to_string()];
+    if let Ok(dir) = bambooclaw_dir() {
+        paths.push(dir.display().to_string());
+    }
+    paths
+}
+
+fn references_any(process: &Process, paths: &[String]) -> bool {
+    process
+        .cmd()
+        .iter()
+        .any(|arg| paths.iter().any(|path| arg.contains(path.as_str())))
+}
+
+fn flushed(process: &Process) -> FlushedProcess {
+    FlushedProcess {
+        pid: process.pid().as_u32(),
+        name: process.name().to_string(),
+    }
+}
+
+fn matches_name(process: &Process, names: &[String]) -> bool {
+    let name = process.name();
+    let name = name.strip_suffix(".exe").unwrap_or(name);
+    names.iter().any(|n| {
+        n.strip_suffix(".exe")
+            .unwrap_or(n)
+            .eq_ignore_ascii_case(name)
+    })
+}
+
+// What `patterns` match inside `dir`. Patterns can't reach outside `dir`, and only a `dir` below
+// the system temp dir or the bambooclaw dir matches anything, so a mistyped `[agent] temp_dir`
+// can't empty the home directory or a whole drive.
+fn matching_temp_files(dir: &Path, patterns: &[String]) -> Vec<PathBuf> {
+    let Ok(dir) = dir.canonicalize() else {
+        return Vec::new();
+    };
+    let inside = |root: PathBuf| {
+        root.canonicalize()
+            .is_ok_and(|root| dir != root && dir.starts_with(&root))
+    };
+    let allowed = inside(std::env::temp_dir()) || bambooclaw_dir().is_ok_and(inside);
+    if !allowed || !dir.is_dir() {
+        return Vec::new();
+    }
+
+    let base = glob::Pattern::escape(&dir.to_string_lossy());
+    let mut matches = Vec::new();
+    for pattern in patterns {
+        let relative = Path::new(pattern);
+        let escapes = relative.is_absolute()
+            || relative
+                .components()
+                .any(|c| matches!(c, Component::ParentDir | Component::Prefix(_)));
+        if escapes {
+            continue;
+        }
+        let Ok(paths) = glob::glob(&format!("{}{}{}", base, std::path::MAIN_SEPARATOR, pattern))
+        else {
+            continue;
+        };
+        matches.extend(paths.flatten());
+    }
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+// A symlink is removed itself, never what it points to
+fn remove_entry(path: &Path) -> std::io::Result<()> {
+    if std::fs::symlink_metadata(path)?.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
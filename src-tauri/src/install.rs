@@ -0,0 +1,475 @@
+use crate::config::{get_config_path, validate_config};
+use crate::daemon::{self, daemon_binary_path, DaemonState, DEFAULT_GRACEFUL_TIMEOUT};
+use crate::download::{partial_path, validator_path};
+use crate::error::AppError;
+use crate::logging::installer_log_files;
+use crate::update::get_bambooclaw_version;
+use crate::wizard::complete_step;
+use crate::{bambooclaw_dir, get_home_dir};
+#[cfg(target_os = "windows")]
+use crate::{run_checked, ShellOptions};
+use serde::Serialize;
+#[cfg(not(target_os = "windows"))]
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// What uninstall removes from the bambooclaw dir besides the binary and the app's own logs. Only
+// entries the app creates are touched: the dir may be BAMBOOCLAW_HOME or the platform config dir,
+// which can hold the user's own files too.
+const APP_ENTRIES: &[&str] = &["daemon.pid", "logs", "install_state.json", "diagnostics"];
+// Kept by uninstall unless `remove_config` is set: the configs and their backups
+const CONFIG_ENTRIES: &[&str] = &["config.toml", "backups", "profiles"];
+
+// Shared start of the PATH scripts. The user PATH is read straight from the registry so the
+// other entries are written back exactly as they were (unexpanded %VARS% included). Set-UserPath
+// saves a new entry list, tells running programs with WM_SETTINGCHANGE, and prints "changed".
+#[cfg(target_os = "windows")]
+const USER_PATH_PRELUDE: &str = r#"
+$dir = $env:BAMBOOCLAW_DIR.TrimEnd('\')
+$path = (Get-Item 'HKCU:\Environment').GetValue('Path', '', 'DoNotExpandEnvironmentNames')
+$all = @($path -split ';' | Where-Object { $_ })
+function Test-InstallDir($entry) { [Environment]::ExpandEnvironmentVariables($entry).TrimEnd('\') -eq $dir }
+function Set-UserPath($entries) {
+    Set-ItemProperty -Path 'HKCU:\Environment' -Name Path -Value ($entries -join ';') -Type ExpandString
+    Add-Type -Namespace BambooClaw -Name Env -MemberDefinition '[DllImport("user32.dll", CharSet = CharSet.Unicode)] public static extern IntPtr SendMessageTimeout(IntPtr hWnd, uint msg, UIntPtr wParam, string lParam, uint flags, uint timeout, out UIntPtr result);'
+    $result = [UIntPtr]::Zero
+    # HWND_BROADCAST, WM_SETTINGCHANGE, SMTO_ABORTIFHUNG
+    [BambooClaw.Env]::SendMessageTimeout([IntPtr]0xffff, 0x1A, [UIntPtr]::Zero, 'Environment', 2, 5000, [ref]$result) | Out-Null
+    'changed'
+}
+"#;
+
+#[cfg(target_os = "windows")]
+const ADD_TO_USER_PATH_SCRIPT: &str = r#"
+if (-not ($all | Where-Object { Test-InstallDir $_ })) { Set-UserPath ($all + $dir) }
+"#;
+
+#[cfg(target_os = "windows")]
+const USER_PATH_HAS_DIR_SCRIPT: &str = r#"
+if ($all | Where-Object { Test-InstallDir $_ }) { 'found' }
+"#;
+
+#[cfg(target_os = "windows")]
+const REMOVE_FROM_USER_PATH_SCRIPT: &str = r#"
+$kept = @($all | Where-Object { -not (Test-InstallDir $_) })
+if ($kept.Count -lt $all.Count) { Set-UserPath $kept }
+"#;
+
+// Lines add_to_path appends to the shell profile on macOS and Linux
+#[cfg(not(target_os = "windows"))]
+const PROFILE_COMMENT: &str = "# Added by BambooClaw";
+
+// Every profile add_to_path may have written to, so uninstall can clean up after a shell change
+#[cfg(not(target_os = "windows"))]
+const SHELL_PROFILES: &[&str] = &[".zshrc", ".bashrc", ".profile"];
+
+// Reply of uninstall
+#[derive(Serialize)]
+pub struct UninstallReport {
+    removed: Vec<String>,
+    kept: Vec<String>,
+    // False if the daemon ignored the exit request and had to be force-killed
+    daemon_stopped_gracefully: bool,
+    path_entry_removed: bool,
+}
+
+// Reply of add_to_path
+#[derive(Serialize)]
+pub struct PathUpdate {
+    // False if the bambooclaw dir was already on the PATH
+    pub(crate) added: bool,
+    // Terminals opened before the change don't see it and must be restarted
+    restart_required: bool,
+    // The shell profile that was checked, or the registry value on Windows
+    pub(crate) location: String,
+}
+
+// One line of verify_installation's checklist. `fix` says what to do when `ok` is false.
+#[derive(Serialize)]
+pub struct InstallationCheck {
+    ok: bool,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl InstallationCheck {
+    fn passed(detail: String) -> Self {
+        InstallationCheck {
+            ok: true,
+            detail,
+            fix: None,
+        }
+    }
+
+    fn failed(detail: String, fix: &str) -> Self {
+        InstallationCheck {
+            ok: false,
+            detail,
+            fix: Some(fix.to_string()),
+        }
+    }
+}
+
+// Reply of verify_installation; `ok` is true when every check passed
+#[derive(Serialize)]
+pub struct InstallationReport {
+    ok: bool,
+    binary: InstallationCheck,
+    config: InstallationCheck,
+    path: InstallationCheck,
+    daemon: InstallationCheck,
+}
+
+// 26. Uninstall bambooclaw: stop the daemon, then delete what the app put in the bambooclaw dir
+// (binary, PID file, logs and install state). config.toml, its backups and the profiles stay
+// unless `remove_config` is set. Anything else in the directory is left alone, and the directory
+// itself is removed only when that leaves it empty. The PATH change
+// made by add_to_path is also undone. The installer creates no shortcuts of its own;
+// the Start Menu entry belongs to this app and is left to its uninstaller.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(app, state), err)]
+pub async fn uninstall(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, DaemonState>,
+    remove_config: bool,
+) -> Result<UninstallReport, AppError> {
+    let daemon_stopped_gracefully =
+        daemon::shutdown_any_daemon(&app, &state, DEFAULT_GRACEFUL_TIMEOUT).await;
+    daemon::remove_pid_file();
+
+    let install_dir = bambooclaw_dir()?;
+    let mut report = UninstallReport {
+        removed: Vec::new(),
+        kept: Vec::new(),
+        daemon_stopped_gracefully,
+        path_entry_removed: remove_from_user_path(&install_dir).await?,
+    };
+
+    if !install_dir.exists() {
+        return Ok(report);
+    }
+    let binary = daemon_binary_path()?;
+    let partial = partial_path(&binary);
+    let mut targets = vec![validator_path(&partial), partial, binary];
+    targets.extend(APP_ENTRIES.iter().map(|name| install_dir.join(name)));
+    targets.extend(installer_log_files()?);
+    let config = CONFIG_ENTRIES.iter().map(|name| install_dir.join(name));
+    if remove_config {
+        targets.extend(config);
+    } else {
+        report.kept.extend(
+            config
+                .filter(|path| path.exists())
+                .map(|path| path.to_string_lossy().to_string()),
+        );
+    }
+
+    for path in targets {
+        if std::fs::symlink_metadata(&path).is_ok() {
+            remove_entry(&path)?;
+            report.removed.push(path.to_string_lossy().to_string());
+        }
+    }
+    // Fails, as it should, while anything is left in it
+    if std::fs::remove_dir(&install_dir).is_ok() {
+        report
+            .removed
+            .push(install_dir.to_string_lossy().to_string());
+    }
+    Ok(report)
+}
+
+// 27. Put the bambooclaw dir on the user's PATH so `bambooclaw` works from a terminal. On Windows
+// this edits the HKCU\Environment Path value; elsewhere it appends an export line to the profile of
+// the user's shell (.zshrc, .bashrc, or .profile for any other shell). Safe to run repeatedly.
+// With `dry_run` nothing is changed; `added` then says whether the directory would be added.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub async fn add_to_path(dry_run: Option<bool>) -> Result<PathUpdate, AppError> {
+    if dry_run.unwrap_or(false) {
+        return plan_path_update().await;
+    }
+    let update = update_user_path().await?;
+    complete_step("path_added");
+    Ok(update)
+}
+
+// What add_to_path would do, without doing it
+pub(crate) async fn plan_path_update() -> Result<PathUpdate, AppError> {
+    let install_dir = bambooclaw_dir()?;
+    #[cfg(target_os = "windows")]
+    let (added, location) = (
+        !user_path_has(&install_dir).await?,
+        "HKCU\\Environment\\Path".to_string(),
+    );
+    #[cfg(not(target_os = "windows"))]
+    let (added, location) = {
+        let home = PathBuf::from(get_home_dir()?);
+        let profile = home.join(shell_profile());
+        let export = profile_export(&install_dir, &home);
+        // Like update_user_path, only the profile counts, not the running app's PATH
+        let added = !std::fs::read_to_string(&profile)
+            .is_ok_and(|content| content.lines().any(|line| line.trim() == export));
+        (added, profile.to_string_lossy().to_string())
+    };
+    Ok(PathUpdate {
+        added,
+        restart_required: added,
+        location,
+    })
+}
+
+// 54. Check the whole installation at once, for the "verify" button after setup: the binary runs
+// and reports its version, config.toml exists and validates without warnings, the bambooclaw dir
+// is on the user's PATH, and the daemon is running or could start. The last one doesn't start
+// it; it checks that the binary and config are usable and the gateway port is free.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", skip(state), err)]
+pub async fn verify_installation(
+    state: tauri::State<'_, DaemonState>,
+) -> Result<InstallationReport, AppError> {
+    let binary = match get_bambooclaw_version().await {
+        Ok(version) => InstallationCheck::passed(format!(
+            "bambooclaw {} at {}",
+            version,
+            daemon_binary_path()?.display()
+        )),
+        Err(e) => InstallationCheck::failed(
+            e.to_string(),
+            "Download the binary again from the setup wizard",
+        ),
+    };
+
+    let config_path = get_config_path(None)?;
+    let config = match std::fs::read_to_string(&config_path) {
+        Ok(content) => match validate_config(content) {
+            Ok(warnings) if warnings.is_empty() => {
+                InstallationCheck::passed(format!("{} is valid", config_path))
+            }
+            Ok(warnings) => InstallationCheck::failed(
+                format!(
+                    "{} has {} problem(s), the first: {}",
+                    config_path,
+                    warnings.len(),
+                    warnings[0].message
+                ),
+                "Fix the listed keys in the settings, or restore a backup",
+            ),
+            Err(e) => InstallationCheck::failed(
+                e.to_string(),
+                "Fix the syntax error, or restore a backup",
+            ),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => InstallationCheck::failed(
+            format!("{} does not exist", config_path),
+            "Save the settings once, or generate the default config",
+        ),
+        Err(e) => InstallationCheck::failed(
+            AppError::io("Failed to read config.toml", e).to_string(),
+            "Check the permissions of the bambooclaw folder",
+        ),
+    };
+
+    let install_dir = bambooclaw_dir()?;
+    let path = match user_path_has(&install_dir).await {
+        Ok(true) => InstallationCheck::passed(format!("{} is on the PATH", install_dir.display())),
+        Ok(false) => InstallationCheck::failed(
+            format!("{} is not on the PATH", install_dir.display()),
+            "Add it to the PATH from the setup wizard, then open a new terminal",
+        ),
+        Err(e) => {
+            InstallationCheck::failed(e.to_string(), "Add it to the PATH from the setup wizard")
+        }
+    };
+
+    let daemon = if let Some(pid) = daemon::current_daemon_pid(&state) {
+        InstallationCheck::passed(format!("The daemon is running (PID {})", pid))
+    } else if !binary.ok || !config.ok {
+        InstallationCheck::failed(
+            "The daemon can't start until the binary and config.toml are fixed".to_string(),
+            "Fix the problems above first",
+        )
+    } else {
+        let port = daemon::gateway_port(&state);
+        match std::net::TcpListener::bind(("127.0.0.1", port)) {
+            Ok(_) => InstallationCheck::passed(format!(
+                "The daemon is stopped and can start on port {}",
+                port
+            )),
+            Err(e) => InstallationCheck::failed(
+                format!("Port {} is not available: {}", port, e),
+                "Close the program using the port, or change [gateway] port",
+            ),
+        }
+    };
+
+    Ok(InstallationReport {
+        ok: binary.ok && config.ok && path.ok && daemon.ok,
+        binary,
+        config,
+        path,
+        daemon,
+    })
+}
+
+async fn update_user_path() -> Result<PathUpdate, AppError> {
+    let install_dir = bambooclaw_dir()?;
+    #[cfg(target_os = "windows")]
+    {
+        let added = run_user_path_script(ADD_TO_USER_PATH_SCRIPT, &install_dir).await? == "changed";
+        Ok(PathUpdate {
+            added,
+            restart_required: added,
+            location: "HKCU\\Environment\\Path".to_string(),
+        })
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let home = PathBuf::from(get_home_dir()?);
+        let profile = home.join(shell_profile());
+        let export = profile_export(&install_dir, &home);
+        let content = match std::fs::read_to_string(&profile) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(AppError::io(
+                    format!("Failed to read {}", profile.display()),
+                    e,
+                ))
+            }
+        };
+        let added = !content.lines().any(|line| line.trim() == export);
+        if added {
+            let separator = if content.is_empty() || content.ends_with('\n') {
+                ""
+            } else {
+                "\n"
+            };
+            let block = format!("{}\n{}\n{}\n", separator, PROFILE_COMMENT, export);
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&profile)
+                .and_then(|mut file| file.write_all(block.as_bytes()))
+                .map_err(|e| AppError::io(format!("Failed to update {}", profile.display()), e))?;
+        }
+        Ok(PathUpdate {
+            added,
+            restart_required: added,
+            location: profile.to_string_lossy().to_string(),
+        })
+    }
+}
+
+// The line add_to_path writes for `dir`. Under the home directory it is written relative to $HOME,
+// as in `export PATH="$HOME/.bambooclaw:$PATH"`.
+#[cfg(not(target_os = "windows"))]
+fn profile_export(dir: &Path, home: &Path) -> String {
+    let dir = match dir.strip_prefix(home) {
+        Ok(relative) => format!("$HOME/{}", relative.display()),
+        Err(_) => dir.display().to_string(),
+    };
+    format!(r#"export PATH="{}:$PATH""#, dir)
+}
+
+// Whether the user's PATH includes `dir`, as new terminals will see it: from the registry on
+// Windows, and from the running app's PATH or the lines add_to_path writes elsewhere
+#[cfg(target_os = "windows")]
+async fn user_path_has(dir: &Path) -> Result<bool, AppError> {
+    Ok(run_user_path_script(USER_PATH_HAS_DIR_SCRIPT, dir).await? == "found")
+}
+
+#[cfg(not(target_os = "windows"))]
+async fn user_path_has(dir: &Path) -> Result<bool, AppError> {
+    let in_env = std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|entry| entry == dir));
+    if in_env {
+        return Ok(true);
+    }
+    let home = PathBuf::from(get_home_dir()?);
+    let export = profile_export(dir, &home);
+    Ok(SHELL_PROFILES.iter().any(|name| {
+        std::fs::read_to_string(home.join(name))
+            .is_ok_and(|content| content.lines().any(|line| line.trim() == export))
+    }))
+}
+
+// Deletes a file or directory tree. A symlink is removed itself, never what it points to.
+fn remove_entry(path: &Path) -> Result<(), AppError> {
+    let metadata = std::fs::symlink_metadata(path)
+        .map_err(|e| AppError::io(format!("Failed to read {}", path.display()), e))?;
+    let result = if metadata.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+    result.map_err(|e| AppError::io(format!("Failed to remove {}", path.display()), e))
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) async fn remove_from_user_path(dir: &Path) -> Result<bool, AppError> {
+    Ok(run_user_path_script(REMOVE_FROM_USER_PATH_SCRIPT, dir).await? == "changed")
+}
+
+// Runs one of the PATH scripts for `dir` and returns what it printed: "changed" if it changed the
+// PATH, "found" if USER_PATH_HAS_DIR_SCRIPT found `dir`
+#[cfg(target_os = "windows")]
+async fn run_user_path_script(script: &str, dir: &Path) -> Result<String, AppError> {
+    let mut options = ShellOptions::default();
+    options.env.insert(
+        "BAMBOOCLAW_DIR".to_string(),
+        dir.to_string_lossy().to_string(),
+    );
+    let stdout = run_checked(
+        "powershell",
+        &[
+            "-NoProfile".to_string(),
+            "-NonInteractive".to_string(),
+            "-Command".to_string(),
+            format!("{}{}", USER_PATH_PRELUDE, script),
+        ],
+        &options,
+    )
+    .await
+    .map_err(|e| e.map_message(|message| format!("Failed to update PATH: {}", message)))?;
+    Ok(stdout.trim().to_string())
+}
+
+// Strips the lines add_to_path wrote from every shell profile that has them
+#[cfg(not(target_os = "windows"))]
+pub(crate) async fn remove_from_user_path(dir: &Path) -> Result<bool, AppError> {
+    let home = PathBuf::from(get_home_dir()?);
+    let export = profile_export(dir, &home);
+    let mut removed = false;
+    for profile in SHELL_PROFILES.iter().map(|name| home.join(name)) {
+        let Ok(content) = std::fs::read_to_string(&profile) else {
+            continue;
+        };
+        let kept: Vec<&str> = content
+            .lines()
+            .filter(|line| line.trim() != PROFILE_COMMENT && line.trim() != export)
+            .collect();
+        if kept.len() == content.lines().count() {
+            continue;
+        }
+        let mut updated = kept.join("\n");
+        if content.ends_with('\n') {
+            updated.push('\n');
+        }
+        std::fs::write(&profile, updated)
+            .map_err(|e| AppError::io(format!("Failed to update {}", profile.display()), e))?;
+        removed = true;
+    }
+    Ok(removed)
+}
+
+// The profile read by new terminals of the user's login shell
+#[cfg(not(target_os = "windows"))]
+fn shell_profile() -> &'static str {
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    match Path::new(&shell).file_name().and_then(|name| name.to_str()) {
+        Some("zsh") => ".zshrc",
+        Some("bash") => ".bashrc",
+        _ => ".profile",
+    }
+}
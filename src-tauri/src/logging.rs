@@ -0,0 +1,203 @@
+use crate::bambooclaw_dir;
+use crate::daemon::daemon_log_files;
+use crate::error::AppError;
+use crate::metrics::{CommandMetrics, MetricsLayer};
+use serde::Serialize;
+use std::path::PathBuf;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, Registry};
+
+// Files are named installer.log.YYYY-MM-DD; the oldest are deleted past MAX_LOG_FILES
+const LOG_FILE_PREFIX: &str = "installer.log";
+const MAX_LOG_FILES: usize = 7;
+const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::INFO;
+
+// Reply of get_log_size and clear_logs: bytes on disk, rotated files included
+#[derive(Serialize)]
+pub struct LogSizes {
+    installer_bytes: u64,
+    daemon_bytes: u64,
+    total_bytes: u64,
+}
+
+// Handle to the active log level, so set_log_level can change it without restarting
+pub struct LogState(reload::Handle<LevelFilter, Registry>);
+
+// Starts writing the app's log to the bambooclaw dir. Every command call is logged when it ends,
+// with its arguments and how long it took; failed calls also log their error. If the log
+// directory can't be created the app runs without a log file. Command timings are collected into
+// `metrics` either way, whatever the log level.
+pub fn init(metrics: CommandMetrics) -> LogState {
+    let (filter, handle) = reload::Layer::new(DEFAULT_LOG_LEVEL);
+    let appender = log_dir().and_then(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_FILE_PREFIX)
+            .max_log_files(MAX_LOG_FILES)
+            .build(dir)
+            .ok()
+    });
+    // The level only filters the log file, so debug-level commands are still timed
+    let file_layer = appender.map(|appender| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(appender)
+            .with_ansi(false)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_filter(filter)
+    });
+    let _ = tracing_subscriber::registry()
+        .with(file_layer)
+        .with(MetricsLayer::new(metrics))
+        .try_init();
+    LogState(handle)
+}
+
+// 28. Return the last `lines` lines of the app's log, oldest first, for the debug panel
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub fn read_logs(lines: usize) -> Result<Vec<String>, AppError> {
+    let files = installer_log_files()?;
+    // Walk back from today's file until there are enough lines
+    let mut tail: Vec<String> = Vec::new();
+    for file in files.iter().rev() {
+        if tail.len() >= lines {
+            break;
+        }
+        let content = std::fs::read_to_string(file)
+            .map_err(|e| AppError::io(format!("Failed to read {}", file.display()), e))?;
+        let needed = lines - tail.len();
+        tail.extend(content.lines().rev().take(needed).map(str::to_string));
+    }
+    tail.reverse();
+    Ok(tail)
+}
+
+// 29. Change how much the app logs: "error", "warn", "info", "debug", "trace" or "off".
+// Lasts until the app exits.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(state), err)]
+pub fn set_log_level(state: tauri::State<LogState>, level: String) -> Result<(), AppError> {
+    let filter: LevelFilter = level.trim().parse().map_err(|_| {
+        AppError::InvalidInput(format!(
+            "Unknown log level '{}'; expected error, warn, info, debug, trace or off",
+            level
+        ))
+    })?;
+    state
+        .0
+        .reload(filter)
+        .map_err(|e| AppError::Other(format!("Failed to change the log level: {}", e)))?;
+    tracing::info!(%filter, "log level changed");
+    Ok(())
+}
+
+// 52. How much disk space the app's and the daemon's logs take, for offering to clear them
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub fn get_log_size() -> Result<LogSizes, AppError> {
+    let installer_bytes = total_size(&installer_log_files()?);
+    let daemon_bytes = total_size(&daemon_log_files()?);
+    Ok(LogSizes {
+        installer_bytes,
+        daemon_bytes,
+        total_bytes: installer_bytes + daemon_bytes,
+    })
+}
+
+// 53. Empty the "installer" (this app's) log, the "daemon" log, or "all" of them, and return
+// the sizes left. Rotated files are deleted. The file currently written to is truncated in place
+// instead, since this app's logger or a running daemon holds it open: both write in append mode,
+// so their next line lands at the start of the emptied file on every platform, where deleting it
+// would leave them writing to a file that is no longer there.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn clear_logs(which: String) -> Result<LogSizes, AppError> {
+    let (installer, daemon) = match which.as_str() {
+        "installer" => (true, false),
+        "daemon" => (false, true),
+        "all" => (true, true),
+        _ => {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown log '{}'; expected installer, daemon or all",
+                which
+            )))
+        }
+    };
+    if installer {
+        clear_files(&installer_log_files()?)?;
+    }
+    if daemon {
+        clear_files(&daemon_log_files()?)?;
+    }
+    get_log_size()
+}
+
+// Truncates the newest of `files` (oldest first) and deletes the rest
+fn clear_files(files: &[PathBuf]) -> Result<(), AppError> {
+    let Some((current, older)) = files.split_last() else {
+        return Ok(());
+    };
+    for file in older {
+        match std::fs::remove_file(file) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(AppError::io(
+                    format!("Failed to delete {}", file.display()),
+                    e,
+                ))
+            }
+        }
+    }
+    match std::fs::OpenOptions::new().write(true).open(current) {
+        Ok(file) => file
+            .set_len(0)
+            .map_err(|e| AppError::io(format!("Failed to clear {}", current.display()), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(AppError::io(
+            format!("Failed to open {}", current.display()),
+            e,
+        )),
+    }
+}
+
+// Missing files count as empty
+fn total_size(files: &[PathBuf]) -> u64 {
+    files
+        .iter()
+        .filter_map(|file| std::fs::metadata(file).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+// The bambooclaw dir itself
+fn log_dir() -> Option<PathBuf> {
+    bambooclaw_dir().ok()
+}
+
+// The app's log files, oldest first
+pub(crate) fn installer_log_files() -> Result<Vec<PathBuf>, AppError> {
+    let dir = log_dir()
+        .ok_or_else(|| AppError::NotFound("Cannot locate the log directory".to_string()))?;
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::io(format!("Failed to read {}", dir.display()), e)),
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(LOG_FILE_PREFIX)
+        })
+        .map(|entry| entry.path())
+        .collect();
+    // The date suffix sorts chronologically
+    files.sort();
+    Ok(files)
+}
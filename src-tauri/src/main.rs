@@ -1,216 +1,871 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::sync::Mutex;
-use std::process::Child;
-use std::path::Path;
+mod archive;
+mod config;
+mod daemon;
+mod diagnostics;
+mod download;
+mod error;
+mod flush;
+mod install;
+mod logging;
+mod metrics;
+mod open;
+mod prerequisites;
+mod progress;
+mod signature;
+mod update;
+mod wizard;
 
-// State manager to keep track of the running background daemon
-struct DaemonState(Mutex<Option<Child>>);
+use daemon::DaemonState;
+use error::AppError;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
 
-// 1. Get the current OS (Windows, macOS, Linux)
+// 1. Get the current OS (Windows, macOS, Linux). The frontend compares the reply against the bare
+// OS name, so the architecture is reported by get_arch and get_system_info instead.
 #[tauri::command]
-fn get_platform() -> String {
+#[tracing::instrument(target = "command", level = "debug")]
+pub(crate) fn get_platform() -> String {
     std::env::consts::OS.to_string()
 }
 
+// 1b. Get the CPU architecture, normalized to the names release assets use (x64, arm64, x86).
+// Anything else is returned as Rust names it (e.g. riscv64).
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug")]
+pub(crate) fn get_arch() -> String {
+    match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        "x86" => "x86",
+        other => other,
+    }
+    .to_string()
+}
+
+#[derive(Serialize)]
+pub(crate) struct SystemInfo {
+    os: String,
+    arch: String,
+    // e.g. "10.0.22631" on Windows or "14.4.1" on macOS; "Unknown" when it can't be determined
+    os_version: String,
+    // Linux running under the Windows Subsystem for Linux; `os` is still "linux"
+    wsl: bool,
+}
+
+// 1c. Get the OS, architecture and OS version together, for choosing binaries and for bug reports
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug")]
+pub(crate) fn get_system_info() -> SystemInfo {
+    SystemInfo {
+        os: get_platform(),
+        arch: get_arch(),
+        os_version: os_info::get().version().to_string(),
+        wsl: is_wsl(),
+    }
+}
+
+// Whether this is Linux under WSL, whose kernel names Microsoft in /proc/version. Linux binaries
+// and paths apply as usual, but Windows programs such as explorer.exe can be run too.
+pub(crate) fn is_wsl() -> bool {
+    cfg!(target_os = "linux")
+        && std::fs::read_to_string("/proc/version")
+            .is_ok_and(|version| version.to_lowercase().contains("microsoft"))
+}
+
 // 2. Get the user's home directory safely across operating systems
 #[tauri::command]
-fn get_home_dir() -> Result<String, String> {
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub(crate) fn get_home_dir() -> Result<String, AppError> {
     #[cfg(target_os = "windows")]
     {
-        std::env::var("USERPROFILE").map_err(|e| e.to_string())
+        std::env::var("USERPROFILE").map_err(|e| AppError::NotFound(format!("USERPROFILE: {}", e)))
     }
     #[cfg(not(target_os = "windows"))]
     {
-        std::env::var("HOME").map_err(|e| e.to_string())
+        std::env::var("HOME").map_err(|e| AppError::NotFound(format!("HOME: {}", e)))
     }
 }
 
-// 3. Execute any shell command and return stdout or stderr (HEADLESS)
+// 2b. Where bambooclaw keeps its binary, config.toml, logs and state. In order: $BAMBOOCLAW_HOME;
+// ~/.bambooclaw when an earlier install already lives there; the platform's config dir
+// ($XDG_CONFIG_HOME/bambooclaw on Linux when that is set, Application Support on macOS, %APPDATA%
+// on Windows); and ~/.bambooclaw otherwise.
 #[tauri::command]
-fn run_shell_command(command_name: String, args: Vec<String>) -> Result<String, String> {
-    run_shell_command_sync(&command_name, &args)
+#[tracing::instrument(target = "command", level = "debug", err)]
+fn get_bambooclaw_config_dir() -> Result<String, AppError> {
+    bambooclaw_dir().map(|dir| dir.to_string_lossy().into_owned())
 }
 
-// 3b. Async version — runs on a background thread so the UI stays responsive
-#[tauri::command]
-async fn run_shell_command_async(command_name: String, args: Vec<String>) -> Result<String, String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        run_shell_command_sync(&command_name, &args)
-    })
-    .await
-    .map_err(|e| format!("Task join error: {}", e))?
+// The resolver behind get_bambooclaw_config_dir. Every path the app builds under the bambooclaw
+// directory starts here, so the app, the daemon's PID file and its logs always agree.
+pub(crate) fn bambooclaw_dir() -> Result<PathBuf, AppError> {
+    if let Some(dir) = std::env::var_os("BAMBOOCLAW_HOME").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    // Only a real install counts: the frontend's session log can create an empty ~/.bambooclaw
+    let legacy = Path::new(&get_home_dir()?).join(".bambooclaw");
+    if ["config.toml", "bambooclaw", "bambooclaw.exe"]
+        .iter()
+        .any(|name| legacy.join(name).exists())
+    {
+        return Ok(legacy);
+    }
+    #[cfg(target_os = "linux")]
+    let platform_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute());
+    #[cfg(not(target_os = "linux"))]
+    let platform_dir = dirs::config_dir();
+    Ok(platform_dir
+        .map(|dir| dir.join("bambooclaw"))
+        .unwrap_or(legacy))
 }
 
-fn run_shell_command_sync(command_name: &str, args: &[String]) -> Result<String, String> {
-    let mut cmd = std::process::Command::new(command_name);
-    cmd.args(args);
+// How often a command with a timeout is checked for completion
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
-    // This block specifically hides the CMD window on Windows
-    #[cfg(target_os = "windows")]
-    {
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        cmd.creation_flags(CREATE_NO_WINDOW);
+// How a shell command is run. The default inherits the app's working directory and
+// environment and waits for the command however long it takes.
+#[derive(Default)]
+pub(crate) struct ShellOptions {
+    // Kill the command if it is still running after this long
+    pub timeout: Option<Duration>,
+    pub cwd: Option<String>,
+    // Added on top of the inherited environment, or replacing it when clear_env is set
+    pub env: HashMap<String, String>,
+    pub clear_env: bool,
+    // When set, only these commands may be launched
+    pub allowed_commands: Option<Vec<String>>,
+}
+
+impl ShellOptions {
+    // Options for a command requested by the frontend. The allowlist is the caller's
+    // `allowed_commands` narrowed by `[shell] allowed_commands` in config.toml, so the frontend
+    // can restrict itself further but never widen what the config permits.
+    fn new(
+        timeout_secs: Option<u64>,
+        cwd: Option<String>,
+        env: Option<HashMap<String, String>>,
+        clear_env: Option<bool>,
+        allowed_commands: Option<Vec<String>>,
+    ) -> Result<Self, AppError> {
+        let allowed_commands = match (allowed_commands, config::shell_allowed_commands()?) {
+            (Some(requested), Some(configured)) => Some(
+                requested
+                    .into_iter()
+                    .filter(|c| configured.contains(c))
+                    .collect(),
+            ),
+            (requested, configured) => requested.or(configured),
+        };
+        Ok(ShellOptions {
+            timeout: timeout_secs.map(Duration::from_secs),
+            cwd,
+            env: env.unwrap_or_default(),
+            clear_env: clear_env.unwrap_or(false),
+            allowed_commands,
+        })
+    }
+
+    pub(crate) fn with_timeout(timeout: Duration) -> Self {
+        ShellOptions {
+            timeout: Some(timeout),
+            ..Default::default()
+        }
     }
+}
+
+// Reply of run_shell_command_full. `exit_code` is -1 when the command was killed by a signal.
+#[derive(Serialize)]
+struct ShellOutput {
+    stdout: String,
+    stderr: String,
+    exit_code: i32,
+}
 
-    let output = cmd.output()
-        .map_err(|e| format!("Failed to execute process '{}': {}", command_name, e))?;
-    
+// Payload of the `shell_output` event
+#[derive(Clone, Serialize)]
+struct ShellOutputLine {
+    stream_id: String,
+    stream: &'static str,
+    line: String,
+}
+
+// Payload of the `shell_complete` event. `exit_code` is None when the command was killed by a
+// signal, by its timeout or by cancel_job.
+#[derive(Clone, Serialize)]
+struct ShellComplete {
+    stream_id: String,
+    exit_code: Option<i32>,
+    timed_out: bool,
+    cancelled: bool,
+}
+
+// How a streaming command ended. `exit_code` is None when it was killed by a signal, by its
+// timeout or by cancel_job.
+struct JobExit {
+    exit_code: Option<i32>,
+    timed_out: bool,
+    cancelled: bool,
+}
+
+// A `shell` progress event, sent when a streaming command starts and when it ends. Its output
+// lines go to the command's own events.
+#[derive(Clone, Serialize)]
+pub(crate) struct ShellProgress {
+    job_id: String,
+    command: String,
+    running: bool,
+    exit_code: Option<i32>,
+}
+
+// The events a streaming command reports on, and how their payloads are built from its id
+struct JobEvents<L, C> {
+    output: &'static str,
+    complete: &'static str,
+    line: fn(String, &'static str, String) -> L,
+    exit: fn(String, JobExit) -> C,
+}
+
+// A streaming command that is still running
+struct Job {
+    pid: u32,
+    // Set by cancel_job, so shell_complete can tell a cancel from a crash
+    cancelled: bool,
+}
+
+// Running streaming commands, keyed by their stream_id or job_id
+#[derive(Default)]
+struct JobState(Mutex<HashMap<String, Job>>);
+
+// 3. Execute any shell command and return stdout or stderr (HEADLESS).
+// The program is started directly and `args` reach it verbatim: nothing is ever passed through
+// a shell interpreter, so quotes, `;`, `|` or `$(...)` in an argument are just text. Callers
+// that really want a shell must launch one explicitly (`sh -c`, `cmd /c`), which an allowlist
+// can forbid.
+// With `timeout_secs`, a command still running after that long is killed. `cwd` sets the
+// working directory; `env` adds variables to the inherited environment, or replaces it
+// entirely when `clear_env` is true. With `allowed_commands`, or `[shell] allowed_commands` in
+// config.toml, any other command is rejected before it starts.
+// The command runs asynchronously, so a slow one never holds up the window.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(args, env, allowed_commands), err)]
+async fn run_shell_command(
+    command_name: String,
+    args: Vec<String>,
+    timeout_secs: Option<u64>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    clear_env: Option<bool>,
+    allowed_commands: Option<Vec<String>>,
+) -> Result<String, AppError> {
+    let options = ShellOptions::new(timeout_secs, cwd, env, clear_env, allowed_commands)?;
+    run_checked(&command_name, &args, &options).await
+}
+
+// 3b. Same as run_shell_command; kept for existing callers
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(args, env, allowed_commands), err)]
+async fn run_shell_command_async(
+    command_name: String,
+    args: Vec<String>,
+    timeout_secs: Option<u64>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    clear_env: Option<bool>,
+    allowed_commands: Option<Vec<String>>,
+) -> Result<String, AppError> {
+    let options = ShellOptions::new(timeout_secs, cwd, env, clear_env, allowed_commands)?;
+    run_checked(&command_name, &args, &options).await
+}
+
+// Runs the command and returns its stdout; a failure status is CommandFailed with its stderr
+pub(crate) async fn run_checked(
+    command_name: &str,
+    args: &[String],
+    options: &ShellOptions,
+) -> Result<String, AppError> {
+    let output = run_command(command_name, args, options).await?;
     let stdout = String::from_utf8_lossy(&output.stdout);
     let stderr = String::from_utf8_lossy(&output.stderr);
-    
+
     if output.status.success() {
         Ok(stdout.to_string())
     } else {
-        Err(format!("Command failed: {}\n{}", output.status, stderr))
+        Err(AppError::CommandFailed(format!(
+            "Command failed: {}\n{}",
+            output.status, stderr
+        )))
     }
 }
 
-// 4. Verify system prerequisites during the boot wizard
-#[tauri::command]
-fn check_prerequisite(name: String) -> Result<String, String> {
-    match name.as_str() {
-        "rustc" => run_shell_command("rustc".to_string(), vec!["--version".to_string()]),
-        "vs_build_tools" => {
-            #[cfg(target_os = "windows")]
-            {
-                let vswhere = "C:\\Program Files (x86)\\Microsoft Visual Studio\\Installer\\vswhere.exe";
-                run_shell_command(vswhere.to_string(), vec!["-latest".to_string(), "-property".to_string(), "installationPath".to_string()])
-            }
-            #[cfg(not(target_os = "windows"))]
-            {
-                Ok("Not required on this OS".to_string())
-            }
-        },
-        _ => Err(format!("Unknown prerequisite: {}", name))
+// Runs the command to completion. Errors only when it couldn't be started or timed out. Every
+// command the app runs to completion goes through here; the child is killed when its timeout
+// passes or the caller stops waiting for it.
+async fn run_command(
+    command_name: &str,
+    args: &[String],
+    options: &ShellOptions,
+) -> Result<std::process::Output, AppError> {
+    let child = tokio::process::Command::from(build_command(command_name, args, options)?)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| AppError::io(format!("Failed to execute process '{}'", command_name), e))?;
+
+    let output = child.wait_with_output();
+    match options.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, output).await.map_err(|_| {
+            AppError::Timeout(format!(
+                "Command timed out after {}s: {}",
+                timeout.as_secs(),
+                command_name
+            ))
+        })?,
+        None => output.await,
     }
+    .map_err(|e| AppError::io(format!("Failed to execute process '{}'", command_name), e))
 }
 
-// 5. Read the config.toml file
-#[tauri::command]
-fn read_config() -> Result<String, String> {
-    let home = get_home_dir()?;
-    let path = Path::new(&home).join(".bambooclaw").join("config.toml");
-    std::fs::read_to_string(path).map_err(|e| e.to_string())
-}
+fn build_command(
+    command_name: &str,
+    args: &[String],
+    options: &ShellOptions,
+) -> Result<std::process::Command, AppError> {
+    // Exact match only: allowing `git` does not allow `/tmp/git`
+    if let Some(allowed) = &options.allowed_commands {
+        if !allowed.iter().any(|c| c == command_name) {
+            return Err(AppError::NotAllowed(format!(
+                "Command '{}' is not in the allowed commands list",
+                command_name
+            )));
+        }
+    }
 
-// 6. Save the config.toml file
-#[tauri::command]
-fn write_config(content: String) -> Result<String, String> {
-    let home = get_home_dir()?;
-    let dir = Path::new(&home).join(".bambooclaw");
-    
-    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
-    
-    let path = dir.join("config.toml");
-    std::fs::write(path, content).map_err(|e| e.to_string())?;
-    Ok("Config written".to_string())
-}
-
-// 7. Download a binary
-#[tauri::command]
-fn download_binary(url: String, dest: String) -> Result<String, String> {
-    run_shell_command("curl".to_string(), vec!["-sL".to_string(), "-o".to_string(), dest, url])
-}
+    let mut cmd = std::process::Command::new(command_name);
+    cmd.args(args);
 
-// 8. Start the BambooClaw background daemon (in-process thread)
-// The agentic loop runs in the JS frontend; this command only toggles
-// a lightweight keep-alive thread so the backend knows the daemon is "on".
-#[tauri::command]
-fn start_daemon(state: tauri::State<DaemonState>) -> Result<String, String> {
-    let mut child_guard = state.0.lock().unwrap();
-    if child_guard.is_some() {
-        return Ok("Daemon is already running".to_string());
+    // Checked up front: a missing directory would otherwise surface as a confusing spawn error
+    if let Some(cwd) = &options.cwd {
+        if !Path::new(cwd).is_dir() {
+            return Err(AppError::NotFound(format!(
+                "Working directory does not exist: {}",
+                cwd
+            )));
+        }
+        cmd.current_dir(cwd);
     }
+    if options.clear_env {
+        cmd.env_clear();
+    }
+    cmd.envs(&options.env);
 
-    // Spawn a no-op placeholder child (sleep) so we have a PID to track.
-    // The real agent logic lives in the webview JS agentic loop.
+    // This block specifically hides the CMD window on Windows
     #[cfg(target_os = "windows")]
-    let child = {
+    {
         use std::os::windows::process::CommandExt;
         const CREATE_NO_WINDOW: u32 = 0x08000000;
-        std::process::Command::new("cmd")
-            .args(["/C", "timeout /t 2147483 /nobreak >nul"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .spawn()
-            .map_err(|e| format!("Failed to start daemon thread: {}", e))?
-    };
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
 
-    #[cfg(not(target_os = "windows"))]
-    let child = std::process::Command::new("sleep")
-        .arg("infinity")
-        .spawn()
-        .map_err(|e| format!("Failed to start daemon thread: {}", e))?;
+    Ok(cmd)
+}
+
+// Waits for `child`, killing it and returning None once `timeout` has passed
+fn wait_with_timeout(
+    child: &mut std::process::Child,
+    timeout: Option<Duration>,
+) -> std::io::Result<Option<std::process::ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(Some);
+    };
 
-    *child_guard = Some(child);
-    Ok("Daemon started".to_string())
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        std::thread::sleep(COMMAND_POLL_INTERVAL);
+    }
 }
 
-// 9. Stop the background daemon (only kills the managed child process)
+// 3c. Streaming version for long-running commands such as `cargo build`. Returns as soon as the
+// command has started. Every output line is emitted as a `shell_output` event tagged with
+// `stream_id`, followed by one `shell_complete` event with the exit code. `stream_id` also
+// names the job for cancel_job, so it must be unique among running commands.
 #[tauri::command]
-fn stop_daemon(state: tauri::State<DaemonState>) -> Result<String, String> {
-    let mut child_guard = state.0.lock().unwrap();
+#[tracing::instrument(target = "command", skip(app, jobs, args, env, allowed_commands), err)]
+#[allow(clippy::too_many_arguments)]
+fn run_shell_command_streaming(
+    app: tauri::AppHandle,
+    jobs: tauri::State<JobState>,
+    command_name: String,
+    args: Vec<String>,
+    stream_id: String,
+    timeout_secs: Option<u64>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    clear_env: Option<bool>,
+    allowed_commands: Option<Vec<String>>,
+) -> Result<(), AppError> {
+    let options = ShellOptions::new(timeout_secs, cwd, env, clear_env, allowed_commands)?;
+    let cmd = build_command(&command_name, &args, &options)?;
+    let events = JobEvents {
+        output: "shell_output",
+        complete: "shell_complete",
+        line: |stream_id, stream, line| ShellOutputLine {
+            stream_id,
+            stream,
+            line,
+        },
+        exit: |stream_id, exit| ShellComplete {
+            stream_id,
+            exit_code: exit.exit_code,
+            timed_out: exit.timed_out,
+            cancelled: exit.cancelled,
+        },
+    };
+    spawn_job(
+        app,
+        &jobs,
+        cmd,
+        &command_name,
+        stream_id,
+        options.timeout,
+        events,
+    )
+}
 
-    if let Some(mut child) = child_guard.take() {
-        let _ = child.kill();
-        let _ = child.wait();
+// Starts `cmd` as job `job_id` and returns once it is running. A thread per pipe emits its lines
+// as `events.output`; when the command ends, `events.complete` follows them.
+fn spawn_job<L, C>(
+    app: tauri::AppHandle,
+    jobs: &JobState,
+    mut cmd: std::process::Command,
+    command_name: &str,
+    job_id: String,
+    timeout: Option<Duration>,
+    events: JobEvents<L, C>,
+) -> Result<(), AppError>
+where
+    L: Serialize + Clone + Send + 'static,
+    C: Serialize + Clone + Send + 'static,
+{
+    // Its own process group, so cancel_job also reaches whatever the command starts
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
     }
-    
-    Ok("Daemon stopped".to_string())
+
+    let mut running = jobs.0.lock().unwrap();
+    if running.contains_key(&job_id) {
+        return Err(AppError::InvalidInput(format!(
+            "A command with id '{}' is already running",
+            job_id
+        )));
+    }
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AppError::io(format!("Failed to execute process '{}'", command_name), e))?;
+    running.insert(
+        job_id.clone(),
+        Job {
+            pid: child.id(),
+            cancelled: false,
+        },
+    );
+    drop(running);
+    let command = command_name.to_string();
+    progress::emit_progress(
+        &app,
+        progress::ProgressEvent::Shell(ShellProgress {
+            job_id: job_id.clone(),
+            command: command.clone(),
+            running: true,
+            exit_code: None,
+        }),
+    );
+
+    let readers = [
+        stream_lines(
+            app.clone(),
+            child.stdout.take(),
+            "stdout",
+            job_id.clone(),
+            events.output,
+            events.line,
+        ),
+        stream_lines(
+            app.clone(),
+            child.stderr.take(),
+            "stderr",
+            job_id.clone(),
+            events.output,
+            events.line,
+        ),
+    ];
+
+    std::thread::spawn(move || {
+        let (exit_code, timed_out) = match wait_with_timeout(&mut child, timeout) {
+            Ok(Some(status)) => {
+                // Let the readers finish so the completion event is the last one of the stream
+                for reader in readers {
+                    let _ = reader.join();
+                }
+                (status.code(), false)
+            }
+            Ok(None) => (None, true),
+            Err(_) => (None, false),
+        };
+        let cancelled = app
+            .state::<JobState>()
+            .0
+            .lock()
+            .unwrap()
+            .remove(&job_id)
+            .is_some_and(|job| job.cancelled);
+        let exit_code = if cancelled { None } else { exit_code };
+        progress::emit_progress(
+            &app,
+            progress::ProgressEvent::Shell(ShellProgress {
+                job_id: job_id.clone(),
+                command,
+                running: false,
+                exit_code,
+            }),
+        );
+        let _ = app.emit_all(
+            events.complete,
+            (events.exit)(
+                job_id,
+                JobExit {
+                    exit_code,
+                    timed_out,
+                    cancelled,
+                },
+            ),
+        );
+    });
+
+    Ok(())
 }
 
-// 10. Emergency Flush — kill all agent-related processes and clean temp files
+// 3e. Kill a command started by run_shell_command_streaming or run_bambooclaw_streaming, along
+// with every process it started. Its completion event then has `cancelled: true`.
 #[tauri::command]
-fn emergency_flush(state: tauri::State<DaemonState>) -> Result<String, String> {
-    // First, stop the managed daemon child process
-    let mut child_guard = state.0.lock().unwrap();
-    if let Some(mut child) = child_guard.take() {
-        let _ = child.kill();
-        let _ = child.wait();
-    }
+#[tracing::instrument(target = "command", skip(jobs), err)]
+async fn cancel_job(jobs: tauri::State<'_, JobState>, job_id: String) -> Result<(), AppError> {
+    let pid = {
+        let mut running = jobs.0.lock().unwrap();
+        let job = running.get_mut(&job_id).ok_or_else(|| {
+            AppError::NotFound(format!("No running command with id '{}'", job_id))
+        })?;
+        job.cancelled = true;
+        job.pid
+    };
 
-    // Kill rogue agent scripts only — NOT bambooclaw.exe (that is this app!)
+    // Negative PID: the whole process group, which the command leads
+    #[cfg(not(target_os = "windows"))]
+    let result = run_checked(
+        "kill",
+        &["-KILL".to_string(), "--".to_string(), format!("-{}", pid)],
+        &ShellOptions::default(),
+    )
+    .await;
+    // /T takes the child processes too
     #[cfg(target_os = "windows")]
-    {
-        let _ = run_shell_command("taskkill".to_string(), vec!["/F".to_string(), "/IM".to_string(), "python.exe".to_string()]);
-        // Clean tmp directory
-        let tmp = Path::new("C:\\tmp");
-        if tmp.exists() {
-            let _ = std::fs::remove_dir_all(tmp);
-            let _ = std::fs::create_dir_all(tmp);
+    let result = run_checked(
+        "taskkill",
+        &[
+            "/F".to_string(),
+            "/T".to_string(),
+            "/PID".to_string(),
+            pid.to_string(),
+        ],
+        &ShellOptions::default(),
+    )
+    .await;
+
+    result.map(|_| ()).map_err(|e| {
+        if let Some(job) = jobs.0.lock().unwrap().get_mut(&job_id) {
+            job.cancelled = false;
         }
-    }
-    #[cfg(not(target_os = "windows"))]
-    {
-        let _ = run_shell_command("pkill".to_string(), vec!["-f".to_string(), "python".to_string()]);
-        // Clean /tmp/bambooclaw if it exists
-        let tmp = Path::new("/tmp/bambooclaw");
-        if tmp.exists() {
-            let _ = std::fs::remove_dir_all(tmp);
-            let _ = std::fs::create_dir_all(tmp);
+        e.map_message(|message| format!("Failed to cancel '{}': {}", job_id, message))
+    })
+}
+
+// Emits each line of `pipe` as an `event` event. Bytes that aren't valid UTF-8 are replaced
+// rather than ending the stream.
+fn stream_lines<L: Serialize + Clone + 'static>(
+    app: tauri::AppHandle,
+    pipe: Option<impl Read + Send + 'static>,
+    stream: &'static str,
+    job_id: String,
+    event: &'static str,
+    payload: fn(String, &'static str, String) -> L,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let Some(pipe) = pipe else { return };
+        let mut reader = BufReader::new(pipe);
+        let mut buf = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut buf), Ok(n) if n > 0) {
+            let line = String::from_utf8_lossy(&buf)
+                .trim_end_matches(['\r', '\n'])
+                .to_string();
+            let _ = app.emit_all(event, payload(job_id.clone(), stream, line));
+            buf.clear();
         }
-    }
+    })
+}
+
+// 3d. Like 3b, but a command that ran is never an error: stdout, stderr and the exit code come
+// back as they are so callers can branch on specific codes (e.g. 127, "not found"). `Err` means
+// the command couldn't be started or hit its timeout.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(args, env, allowed_commands), err)]
+async fn run_shell_command_full(
+    command_name: String,
+    args: Vec<String>,
+    timeout_secs: Option<u64>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    clear_env: Option<bool>,
+    allowed_commands: Option<Vec<String>>,
+) -> Result<ShellOutput, AppError> {
+    let options = ShellOptions::new(timeout_secs, cwd, env, clear_env, allowed_commands)?;
+    let output = run_command(&command_name, &args, &options).await?;
+
+    Ok(ShellOutput {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        // Killed by a signal, so there is no exit code
+        exit_code: output.status.code().unwrap_or(-1),
+    })
+}
+
+// Reply of run_bambooclaw. `exit_code` is None when the run was ended by a signal, which `signal`
+// then names (e.g. 9 for SIGKILL); signals only exist on Unix.
+#[derive(Serialize)]
+struct BambooclawOutput {
+    stdout: String,
+    stderr: String,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+}
+
+// 3f. Run the installed bambooclaw binary with `args` (e.g. ["status"]) and wait for it. As in 3d,
+// a run that ends badly is still a reply, so callers can act on bambooclaw's exit codes; `Err`
+// means it couldn't be started or hit `timeout_secs`.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+async fn run_bambooclaw(
+    args: Vec<String>,
+    timeout_secs: Option<u64>,
+) -> Result<BambooclawOutput, AppError> {
+    let binary = daemon::daemon_binary_path()?;
+    let options = ShellOptions {
+        timeout: timeout_secs.map(Duration::from_secs),
+        ..Default::default()
+    };
+    let output = run_command(&binary.to_string_lossy(), &args, &options).await?;
+
+    #[cfg(unix)]
+    let signal = std::os::unix::process::ExitStatusExt::signal(&output.status);
+    #[cfg(not(unix))]
+    let signal = None;
+    Ok(BambooclawOutput {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        exit_code: output.status.code(),
+        signal,
+    })
+}
 
-    Ok("Emergency flush complete: processes killed, temp files cleaned".to_string())
+// Payload of the `bambooclaw_output` event: one line of a run_bambooclaw_streaming run
+#[derive(Clone, Serialize)]
+struct BambooclawOutputLine {
+    job_id: String,
+    stream: &'static str,
+    line: String,
+}
+
+// Payload of the `bambooclaw_complete` event, fields as in ShellComplete
+#[derive(Clone, Serialize)]
+struct BambooclawComplete {
+    job_id: String,
+    exit_code: Option<i32>,
+    timed_out: bool,
+    cancelled: bool,
+}
+
+// 3g. Streaming version of 3f for long runs such as `bambooclaw agent`. Returns once bambooclaw has
+// started; each output line is emitted as a `bambooclaw_output` event tagged with `job_id`, and a
+// final `bambooclaw_complete` event carries the exit code. cancel_job stops the run.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(app, jobs), err)]
+fn run_bambooclaw_streaming(
+    app: tauri::AppHandle,
+    jobs: tauri::State<JobState>,
+    args: Vec<String>,
+    job_id: String,
+) -> Result<(), AppError> {
+    let binary = daemon::daemon_binary_path()?.to_string_lossy().into_owned();
+    let cmd = build_command(&binary, &args, &ShellOptions::default())?;
+    let events = JobEvents {
+        output: "bambooclaw_output",
+        complete: "bambooclaw_complete",
+        line: |job_id, stream, line| BambooclawOutputLine {
+            job_id,
+            stream,
+            line,
+        },
+        exit: |job_id, exit| BambooclawComplete {
+            job_id,
+            exit_code: exit.exit_code,
+            timed_out: exit.timed_out,
+            cancelled: exit.cancelled,
+        },
+    };
+    spawn_job(app, &jobs, cmd, &binary, job_id, None, events)
 }
 
 fn main() {
+    let command_metrics = metrics::CommandMetrics::default();
     tauri::Builder::default()
         .manage(DaemonState(Mutex::new(None)))
+        .manage(daemon::DaemonLogCapture::default())
+        .manage(daemon::WatchdogState::default())
+        .manage(download::DownloadState::default())
+        .manage(archive::ExtractState::default())
+        .manage(prerequisites::PrerequisiteCache::default())
+        .manage(logging::init(command_metrics.clone()))
+        .manage(command_metrics)
+        .manage(JobState::default())
+        .manage(flush::FlushTokens::default())
+        .setup(|app| {
+            let window = app.get_window("main").unwrap();
+
+            // Attempt to force the window to the foreground.
+            // All calls are best-effort — failures are ignored so the app
+            // always launches even if focus-forcing is denied by the OS.
+            let _ = window.set_always_on_top(true);
+            let _ = window.center();
+            let _ = window.set_focus();
+
+            let w = window.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(500));
+                let _ = w.set_always_on_top(false);
+            });
+
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_platform,
+            get_arch,
+            get_system_info,
             get_home_dir,
+            get_bambooclaw_config_dir,
             run_shell_command,
             run_shell_command_async,
-            check_prerequisite,
-            read_config,
-            write_config,
-            download_binary,
-            start_daemon,
-            stop_daemon,
-            emergency_flush
+            run_shell_command_streaming,
+            run_shell_command_full,
+            run_bambooclaw,
+            run_bambooclaw_streaming,
+            cancel_job,
+            prerequisites::check_prerequisite,
+            prerequisites::check_all_prerequisites,
+            prerequisites::check_disk_space,
+            prerequisites::get_disk_usage,
+            prerequisites::check_network,
+            prerequisites::set_prerequisite_cache_ttl,
+            prerequisites::clear_prerequisite_cache,
+            config::read_config,
+            config::write_config,
+            config::get_config_value,
+            config::set_config_value,
+            config::set_config_section,
+            config::list_config_backups,
+            config::restore_config_backup,
+            config::generate_default_config,
+            config::validate_config,
+            config::migrate_config,
+            config::export_config_to_json,
+            config::export_redacted_config,
+            config::get_config_path,
+            config::open_config_in_file_manager,
+            config::import_config_from_json,
+            config::list_profiles,
+            config::create_profile,
+            config::delete_profile,
+            config::get_agent_temp_dir,
+            download::download_binary,
+            download::cancel_download,
+            download::download_batch,
+            download::reinstall,
+            daemon::start_daemon,
+            daemon::stop_daemon,
+            daemon::daemon_status,
+            daemon::ping_daemon,
+            daemon::get_daemon_resource_usage,
+            daemon::restart_daemon,
+            daemon::set_daemon_log_capture,
+            daemon::enable_daemon_watchdog,
+            daemon::adopt_existing_daemon,
+            daemon::reload_daemon_config,
+            flush::request_emergency_flush,
+            flush::confirm_emergency_flush,
+            flush::list_bambooclaw_processes,
+            flush::kill_process,
+            install::uninstall,
+            install::add_to_path,
+            install::verify_installation,
+            update::check_for_update,
+            update::resolve_binary_url,
+            update::get_bambooclaw_version,
+            update::check_bambooclaw_version,
+            update::validate_binary,
+            logging::read_logs,
+            logging::set_log_level,
+            logging::get_log_size,
+            logging::clear_logs,
+            metrics::get_command_metrics,
+            open::open_path,
+            open::open_url,
+            diagnostics::export_diagnostics,
+            archive::extract_archive,
+            archive::cancel_extract,
+            signature::verify_signature,
+            wizard::get_install_state,
+            wizard::set_install_step,
+            wizard::get_last_install_info,
+            wizard::rollback_install,
+            wizard::plan_install
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                daemon::on_app_exit(app);
+            }
+        });
 }
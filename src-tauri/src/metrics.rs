@@ -0,0 +1,113 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::span;
+use tracing_subscriber::filter::{filter_fn, Filtered};
+use tracing_subscriber::layer::{Context, Filter, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+// Target of every command's span. Each command sets it in its #[tracing::instrument] attribute,
+// which only takes a literal, so it is spelled out there too.
+const COMMAND_TARGET: &str = "command";
+
+// How often one command was called since the app started and how long its calls took
+#[derive(Clone, Default, Serialize)]
+pub struct CommandTiming {
+    call_count: u64,
+    total_ms: f64,
+    max_ms: f64,
+    last_ms: f64,
+}
+
+// Timings keyed by command name, filled in by MetricsLayer and read by get_command_metrics
+#[derive(Clone, Default)]
+pub struct CommandMetrics(Arc<Mutex<HashMap<&'static str, CommandTiming>>>);
+
+// 57. Call counts and durations of every command called so far, keyed by command name, for
+// finding what makes the wizard slow. Durations are wall-clock time from the call to the reply,
+// so an async command's waits on the network or a child process count too.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", skip(metrics))]
+pub fn get_command_metrics(
+    metrics: tauri::State<CommandMetrics>,
+) -> HashMap<&'static str, CommandTiming> {
+    metrics.0.lock().unwrap().clone()
+}
+
+// Times every command. Each one has a span from its #[tracing::instrument] attribute, named
+// after the command and open from the call until the command returns, so the span's lifetime is
+// the command's duration. Only spans with COMMAND_TARGET count: the HTTP client and other
+// libraries open spans of their own. A command called from inside another one is part of the
+// outer call and isn't counted again. Events are left to the log file layer.
+pub struct MetricsLayer(CommandMetrics);
+
+impl MetricsLayer {
+    pub fn new<S>(metrics: CommandMetrics) -> Filtered<Self, impl Filter<S>, S>
+    where
+        S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    {
+        MetricsLayer(metrics).with_filter(filter_fn(|metadata| {
+            metadata.is_span() && metadata.target() == COMMAND_TARGET
+        }))
+    }
+}
+
+// Stored in each span's extensions when it opens
+struct StartedAt(Instant);
+
+impl<S> Layer<S> for MetricsLayer
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(StartedAt(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        if span.parent().is_some() {
+            return;
+        }
+        let Some(elapsed) = span.extensions().get::<StartedAt>().map(|s| s.0.elapsed()) else {
+            return;
+        };
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        let mut metrics = self.0 .0.lock().unwrap();
+        let timing = metrics.entry(span.name()).or_default();
+        timing.call_count += 1;
+        timing.total_ms += ms;
+        timing.max_ms = timing.max_ms.max(ms);
+        timing.last_ms = ms;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    // Library spans and a command called from inside another one are left out of the timings
+    #[test]
+    fn only_outermost_command_spans_are_timed() {
+        let metrics = CommandMetrics::default();
+        let subscriber = tracing_subscriber::registry().with(MetricsLayer::new(metrics.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!(target: "command", "verify_installation").in_scope(|| {
+                tracing::info_span!(target: "command", "get_bambooclaw_version").in_scope(|| {});
+            });
+            tracing::info_span!(target: "hyper::client", "connect").in_scope(|| {});
+        });
+
+        let timings = metrics.0.lock().unwrap();
+        assert_eq!(
+            timings.keys().copied().collect::<Vec<_>>(),
+            ["verify_installation"]
+        );
+        assert_eq!(timings["verify_installation"].call_count, 1);
+    }
+}
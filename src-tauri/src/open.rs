@@ -0,0 +1,117 @@
+use crate::config::wsl_windows_path;
+use crate::download::allowed_dirs;
+use crate::error::AppError;
+use std::ffi::OsString;
+use std::path::{Component, Path};
+use std::process::Command;
+
+// 58. Show a file or folder in the file manager. A folder is opened; a file is only revealed in
+// its folder (selected in Explorer or Finder, its folder opened on Linux), never opened, since
+// opening a downloaded program would run it and sidestep `[shell] allowed_commands`. Relative
+// paths are taken from the bambooclaw dir. Only the bambooclaw dir and `[downloads] allowed_dirs`
+// can be shown.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn open_path(path: String) -> Result<(), AppError> {
+    let outside = || AppError::NotAllowed(format!("'{}' is outside the allowed directories", path));
+    if path.trim().is_empty()
+        || Path::new(&path)
+            .components()
+            .any(|c| c == Component::ParentDir)
+    {
+        return Err(outside());
+    }
+    let target = crate::bambooclaw_dir()?
+        .join(&path)
+        .canonicalize()
+        .map_err(|e| AppError::io(format!("Failed to open {}", path), e))?;
+    if !allowed_dirs()?.iter().any(|dir| target.starts_with(dir)) {
+        return Err(outside());
+    }
+    // Finder launches an .app bundle it is asked to open, folder or not
+    let is_bundle = target.extension().is_some_and(|ext| ext == "app");
+    if target.is_dir() && !is_bundle {
+        open_dir(&target)
+    } else {
+        reveal_in_file_manager(&target)
+    }
+}
+
+// 59. Open an http(s) URL in the default browser, e.g. the documentation
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn open_url(url: String) -> Result<(), AppError> {
+    let parsed = reqwest::Url::parse(url.trim())
+        .map_err(|e| AppError::InvalidInput(format!("Invalid URL '{}': {}", url, e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(AppError::InvalidInput(format!(
+            "Only http and https URLs can be opened, not '{}'",
+            url
+        )));
+    }
+    spawn(file_manager_command(parsed.as_str().into()))
+}
+
+// Opens a directory in the file manager
+fn open_dir(dir: &Path) -> Result<(), AppError> {
+    let target = wsl_windows_path(dir)
+        .map(OsString::from)
+        .unwrap_or_else(|| dir.as_os_str().to_owned());
+    spawn(file_manager_command(target))
+}
+
+// Shows `path` selected in Explorer or Finder. Linux file managers have no common way to select
+// a file, so its folder is opened instead, except under WSL, which usually has no file manager of
+// its own and uses Windows' Explorer.
+pub(crate) fn reveal_in_file_manager(path: &Path) -> Result<(), AppError> {
+    #[cfg(target_os = "windows")]
+    let cmd = {
+        let mut cmd = Command::new("explorer");
+        cmd.arg(format!("/select,{}", path.display()));
+        cmd
+    };
+    #[cfg(target_os = "macos")]
+    let cmd = {
+        let mut cmd = Command::new("open");
+        cmd.arg("-R").arg(path);
+        cmd
+    };
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let cmd = match wsl_windows_path(path) {
+        Some(windows_path) => {
+            let mut cmd = Command::new("explorer.exe");
+            cmd.arg(format!("/select,{}", windows_path));
+            cmd
+        }
+        None => file_manager_command(path.parent().unwrap_or(path).as_os_str().to_owned()),
+    };
+    spawn(cmd)
+}
+
+// explorer, open or xdg-open with `target`: a folder or URL, which they hand to the file manager
+// or browser
+fn file_manager_command(target: OsString) -> Command {
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let program = if crate::is_wsl() {
+        "explorer.exe"
+    } else {
+        "xdg-open"
+    };
+    let mut cmd = Command::new(program);
+    cmd.arg(target);
+    cmd
+}
+
+// Not waited for: explorer exits with 1 even when it worked
+fn spawn(mut cmd: Command) -> Result<(), AppError> {
+    cmd.spawn().map(drop).map_err(|e| {
+        AppError::io(
+            format!("Failed to run {}", cmd.get_program().to_string_lossy()),
+            e,
+        )
+    })
+}
@@ -0,0 +1,498 @@
+use crate::download::http_client;
+use crate::error::AppError;
+use crate::wizard::complete_step;
+use crate::{run_checked, ShellOptions};
+use futures_util::future::join_all;
+use semver::Version;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use sysinfo::Disks;
+use tauri::Manager;
+
+// Upper bound for each prerequisite check, so a hung tool can't stall the boot wizard
+const PREREQUISITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// `docker info` talks to the daemon, which can be slow to answer while it starts up
+const DOCKER_INFO_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long a check result is reused before the tool is asked again
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+// Recent check results by prerequisite name, so moving back and forth through the wizard
+// doesn't re-run slow tools. Failures are cached too; install actions clear the cache since
+// they change the answers.
+pub struct PrerequisiteCache(Mutex<CachedChecks>);
+
+struct CachedChecks {
+    ttl: Duration,
+    entries: HashMap<String, (Instant, Result<PrerequisiteCheck, AppError>)>,
+}
+
+impl Default for PrerequisiteCache {
+    fn default() -> Self {
+        PrerequisiteCache(Mutex::new(CachedChecks {
+            ttl: DEFAULT_CACHE_TTL,
+            entries: HashMap::new(),
+        }))
+    }
+}
+
+impl PrerequisiteCache {
+    // The cached result for `name` if it is fresh, otherwise a new check. The lock isn't held
+    // while a check runs, so checks of different tools still run concurrently.
+    async fn check(&self, name: &str, force: bool) -> Result<PrerequisiteCheck, AppError> {
+        if !force {
+            let cached = self.0.lock().unwrap();
+            if let Some((checked_at, result)) = cached.entries.get(name) {
+                if checked_at.elapsed() < cached.ttl {
+                    return result.clone();
+                }
+            }
+        }
+
+        let result = run_check(name).await;
+        self.0
+            .lock()
+            .unwrap()
+            .entries
+            .insert(name.to_string(), (Instant::now(), result.clone()));
+        result
+    }
+
+    pub(crate) fn clear(&self) {
+        self.0.lock().unwrap().entries.clear();
+    }
+}
+
+// Reply of check_prerequisite. `detail` is what the tool reported (usually its `--version`
+// line); `version` is the version parsed from it, if there is one. `command` is the program that
+// answered, e.g. `python3` or `python` for the "python" check.
+#[derive(Clone, Serialize)]
+pub struct PrerequisiteCheck {
+    detail: String,
+    version: Option<String>,
+    command: Option<String>,
+}
+
+// An entry of check_all_prerequisites. `detail` is the error message when `ok` is false.
+#[derive(Serialize)]
+pub struct PrereqResult {
+    ok: bool,
+    detail: String,
+    version: Option<String>,
+}
+
+// Default target of check_network: where release binaries are published
+const DEFAULT_NETWORK_CHECK_URL: &str = "https://github.com";
+const NETWORK_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Reply of check_network. On failure `error` says why, as one of "dns" (the host name didn't
+// resolve), "connection_refused", "timeout" or "other"; `detail` has the underlying message.
+// Any HTTP response, even an error status, counts as reachable.
+#[derive(Serialize)]
+pub struct NetworkCheck {
+    url: String,
+    reachable: bool,
+    latency_ms: Option<u64>,
+    status: Option<u16>,
+    error: Option<&'static str>,
+    detail: Option<String>,
+}
+
+// Reply of check_disk_space
+#[derive(Serialize)]
+pub struct DiskSpace {
+    mount_point: String,
+    available_bytes: u64,
+    required_bytes: u64,
+}
+
+// Reply of get_disk_usage. sysinfo reports a single free figure, the space this user can write
+// to, so `free_bytes` and `available_bytes` are equal; blocks reserved for root are not counted.
+#[derive(Serialize)]
+pub struct DiskUsage {
+    mount_point: String,
+    total_bytes: u64,
+    free_bytes: u64,
+    available_bytes: u64,
+}
+
+// 4. Verify system prerequisites during the boot wizard. With `min_version` (e.g. "1.74"), a tool
+// that is installed but older than that is reported as an error. Results are cached for a short
+// while; `force` runs the check again regardless.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(cache), err)]
+pub async fn check_prerequisite(
+    cache: tauri::State<'_, PrerequisiteCache>,
+    name: String,
+    min_version: Option<String>,
+    force: Option<bool>,
+) -> Result<PrerequisiteCheck, AppError> {
+    let check = cache.check(&name, force.unwrap_or(false)).await?;
+    if let Some(min_version) = min_version {
+        let version = check.version.as_deref().and_then(lenient_version);
+        check_min_version(&name, &check.detail, version.as_ref(), &min_version)?;
+    }
+    Ok(check)
+}
+
+// Runs the check for `name`, uncached
+async fn run_check(name: &str) -> Result<PrerequisiteCheck, AppError> {
+    let (command, detail) = match name {
+        "rustc" | "cargo" | "git" | "node" | "docker" => {
+            (name.to_string(), tool_version(name).await?)
+        }
+        // Either name may be missing or a stub, depending on the OS and how Python was installed
+        "python" | "python3" => first_tool_version(&["python3", "python"]).await?,
+        "vs_build_tools" => {
+            return Ok(PrerequisiteCheck {
+                detail: vs_build_tools().await?,
+                version: None,
+                command: None,
+            })
+        }
+        _ => {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown prerequisite: {}",
+                name
+            )))
+        }
+    };
+
+    let detail = detail.trim().to_string();
+    // The CLI alone is not enough: the agent needs a running daemon to start containers
+    if name == "docker" {
+        docker_daemon_reachable(&detail).await?;
+    }
+    Ok(PrerequisiteCheck {
+        version: parse_version(&detail).map(|v| v.to_string()),
+        detail,
+        command: Some(command),
+    })
+}
+
+// 4b. Run several prerequisite checks at once, so the boot wizard fills its whole checklist in one
+// round-trip. Failed or unknown checks are reported in their own entry, never as a failure of the
+// call. `min_versions` maps a name to the `min_version` of its check; `force` bypasses the cache
+// as in check_prerequisite.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(app))]
+pub async fn check_all_prerequisites(
+    app: tauri::AppHandle,
+    names: Vec<String>,
+    min_versions: Option<HashMap<String, String>>,
+    force: Option<bool>,
+) -> HashMap<String, PrereqResult> {
+    let mut min_versions = min_versions.unwrap_or_default();
+    let checks = names.into_iter().map(|name| {
+        let min_version = min_versions.remove(&name);
+        let app = app.clone();
+        async move {
+            let result = check_prerequisite(app.state(), name.clone(), min_version, force).await;
+            (name, result)
+        }
+    });
+
+    let results = join_all(checks).await;
+    complete_step("prereqs_checked");
+    results
+        .into_iter()
+        .map(|(name, result)| {
+            let result = match result {
+                Ok(check) => PrereqResult {
+                    ok: true,
+                    detail: check.detail,
+                    version: check.version,
+                },
+                Err(error) => PrereqResult {
+                    ok: false,
+                    detail: error.to_string(),
+                    version: None,
+                },
+            };
+            (name, result)
+        })
+        .collect()
+}
+
+// 4c. Check that the volume holding `path` has at least `required_bytes` free, so a download
+// can be refused up front instead of failing halfway with a write error. `path` doesn't need to
+// exist yet; its nearest existing parent decides the volume.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn check_disk_space(path: String, required_bytes: u64) -> Result<DiskSpace, AppError> {
+    let usage = get_disk_usage(path)?;
+    let space = DiskSpace {
+        mount_point: usage.mount_point,
+        available_bytes: usage.available_bytes,
+        required_bytes,
+    };
+    if space.available_bytes < required_bytes {
+        return Err(AppError::InsufficientDiskSpace(format!(
+            "Not enough disk space on {}: {} required, {} available",
+            space.mount_point,
+            format_bytes(required_bytes),
+            format_bytes(space.available_bytes)
+        )));
+    }
+    Ok(space)
+}
+
+// 4g. Size and free space of the volume holding `path`, for a live space indicator. As in 4c,
+// `path` doesn't need to exist yet.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub fn get_disk_usage(path: String) -> Result<DiskUsage, AppError> {
+    let target = existing_ancestor(Path::new(&path))
+        .ok_or_else(|| AppError::NotFound(format!("Cannot resolve path '{}'", path)))?;
+
+    // The most specific mount point containing the path
+    let disks = Disks::new_with_refreshed_list();
+    let disk = disks
+        .list()
+        .iter()
+        .filter(|disk| target.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().components().count())
+        .ok_or_else(|| AppError::NotFound(format!("Cannot find the disk holding '{}'", path)))?;
+
+    Ok(DiskUsage {
+        mount_point: disk.mount_point().display().to_string(),
+        total_bytes: disk.total_space(),
+        free_bytes: disk.available_space(),
+        available_bytes: disk.available_space(),
+    })
+}
+
+// 4d. Check that the release host (or `url`) can be reached, with a HEAD request and a short
+// timeout, and measure the round trip. Proxies from the environment are honoured like in
+// download_binary.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub async fn check_network(url: Option<String>) -> Result<NetworkCheck, AppError> {
+    let url = url.unwrap_or_else(|| DEFAULT_NETWORK_CHECK_URL.to_string());
+    let client = http_client(None)?;
+
+    let started = Instant::now();
+    let result = client
+        .head(&url)
+        .timeout(NETWORK_CHECK_TIMEOUT)
+        .send()
+        .await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    Ok(match result {
+        Ok(response) => NetworkCheck {
+            url,
+            reachable: true,
+            latency_ms: Some(latency_ms),
+            status: Some(response.status().as_u16()),
+            error: None,
+            detail: None,
+        },
+        Err(e) => NetworkCheck {
+            url,
+            reachable: false,
+            latency_ms: None,
+            status: None,
+            error: Some(network_error_kind(&e)),
+            detail: Some(describe_error(&e)),
+        },
+    })
+}
+
+// reqwest's own message ("error sending request for url ...") hides the cause; add the
+// innermost one, e.g. "failed to lookup address information"
+fn describe_error(err: &reqwest::Error) -> String {
+    let mut root = None;
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        root = Some(cause);
+        source = cause.source();
+    }
+    match root {
+        Some(root) => format!("{}: {}", err, root),
+        None => err.to_string(),
+    }
+}
+
+// reqwest only tells timeouts and connect errors apart; the cause chain has the rest
+fn network_error_kind(err: &reqwest::Error) -> &'static str {
+    if err.is_timeout() {
+        return "timeout";
+    }
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            match io.kind() {
+                std::io::ErrorKind::ConnectionRefused => return "connection_refused",
+                std::io::ErrorKind::TimedOut => return "timeout",
+                _ => {}
+            }
+        }
+        if cause.to_string().contains("dns error") {
+            return "dns";
+        }
+        source = cause.source();
+    }
+    "other"
+}
+
+// `path` or its closest parent that exists, with symlinks resolved
+fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+    let resolved = existing.canonicalize().ok()?;
+    // canonicalize returns `\\?\C:\...` on Windows, which never matches a `C:\` mount point
+    #[cfg(target_os = "windows")]
+    if let Some(plain) = resolved.to_str().and_then(|s| s.strip_prefix(r"\\?\")) {
+        return Some(PathBuf::from(plain));
+    }
+    Some(resolved)
+}
+
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const GB: u64 = 1024 * 1024 * 1024;
+    const MB: u64 = 1024 * 1024;
+    if bytes >= GB {
+        format!("{:.1} GB", bytes as f64 / GB as f64)
+    } else {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    }
+}
+
+// 4e. Set how long prerequisite results are reused (30s by default); 0 disables the cache
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(cache))]
+pub fn set_prerequisite_cache_ttl(cache: tauri::State<PrerequisiteCache>, ttl_secs: u64) {
+    cache.0.lock().unwrap().ttl = Duration::from_secs(ttl_secs);
+}
+
+// 4f. Forget every cached prerequisite result. Call after installing or removing a tool.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(cache))]
+pub fn clear_prerequisite_cache(cache: tauri::State<PrerequisiteCache>) {
+    cache.clear();
+}
+
+// `<program> --version`
+async fn tool_version(program: &str) -> Result<String, AppError> {
+    run_checked(
+        program,
+        &["--version".to_string()],
+        &ShellOptions::with_timeout(PREREQUISITE_TIMEOUT),
+    )
+    .await
+}
+
+// `--version` of the first of `programs` that runs, along with its name
+async fn first_tool_version(programs: &[&str]) -> Result<(String, String), AppError> {
+    let mut last_error = AppError::Other(String::new());
+    for program in programs {
+        match tool_version(program).await {
+            Ok(detail) => return Ok((program.to_string(), detail)),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error.map_message(|message| {
+        format!("None of {} could be run: {}", programs.join(", "), message)
+    }))
+}
+
+async fn docker_daemon_reachable(detail: &str) -> Result<(), AppError> {
+    run_checked(
+        "docker",
+        &[
+            "info".to_string(),
+            "--format".to_string(),
+            "{{.ServerVersion}}".to_string(),
+        ],
+        &ShellOptions::with_timeout(DOCKER_INFO_TIMEOUT),
+    )
+    .await
+    .map(|_| ())
+    .map_err(|e| {
+        e.map_message(|message| {
+            format!(
+                "{} is installed, but the Docker daemon is not reachable: {}",
+                detail,
+                message.trim()
+            )
+        })
+    })
+}
+
+async fn vs_build_tools() -> Result<String, AppError> {
+    #[cfg(target_os = "windows")]
+    {
+        let vswhere = "C:\\Program Files (x86)\\Microsoft Visual Studio\\Installer\\vswhere.exe";
+        run_checked(
+            vswhere,
+            &[
+                "-latest".to_string(),
+                "-property".to_string(),
+                "installationPath".to_string(),
+            ],
+            &ShellOptions::with_timeout(PREREQUISITE_TIMEOUT),
+        )
+        .await
+        .map(|path| path.trim().to_string())
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        Ok("Not required on this OS".to_string())
+    }
+}
+
+fn check_min_version(
+    name: &str,
+    detail: &str,
+    found: Option<&Version>,
+    min_version: &str,
+) -> Result<(), AppError> {
+    let min = lenient_version(min_version.trim().trim_start_matches('v')).ok_or_else(|| {
+        AppError::InvalidInput(format!("Invalid minimum version '{}'", min_version))
+    })?;
+    match found {
+        None => Err(AppError::Other(format!(
+            "Could not determine the {} version from '{}'",
+            name,
+            detail.lines().next().unwrap_or_default()
+        ))),
+        Some(found) if *found < min => Err(AppError::VersionTooOld(format!(
+            "{} {} found, {}+ required",
+            name, found, min_version
+        ))),
+        Some(_) => Ok(()),
+    }
+}
+
+// The first version-looking token of a `--version` line, e.g. `rustc 1.74.0 (79e9716c9
+// 2023-11-13)`, `git version 2.39.2.windows.1` or `v20.10.0`
+pub(crate) fn parse_version(output: &str) -> Option<Version> {
+    output.split_whitespace().find_map(|token| {
+        let token = token.strip_prefix('v').unwrap_or(token);
+        let numeric: String = token
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        if !numeric.contains('.') {
+            return None;
+        }
+        lenient_version(&numeric)
+    })
+}
+
+// Tools rarely print strict semver: accepts `1`, `1.74` or `1.74.0.1`, padding or truncating
+// to major.minor.patch
+pub(crate) fn lenient_version(version: &str) -> Option<Version> {
+    let mut parts = version
+        .split('.')
+        .filter(|part| !part.is_empty())
+        .map(str::parse::<u64>);
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().transpose().ok()?.unwrap_or(0);
+    let patch = parts.next().transpose().ok()?.unwrap_or(0);
+    Some(Version::new(major, minor, patch))
+}
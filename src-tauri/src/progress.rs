@@ -0,0 +1,42 @@
+use crate::archive::ExtractProgress;
+use crate::daemon::DaemonProgress;
+use crate::download::DownloadProgress;
+use crate::ShellProgress;
+use serde::Serialize;
+use tauri::Manager;
+
+// The event every long-running operation reports its progress on
+const PROGRESS_EVENT: &str = "progress";
+
+// Sent as `version` in every payload. Bump it when a payload changes in a way that breaks a
+// listener, such as a renamed or removed field; new fields don't need a bump.
+const PROGRESS_VERSION: u32 = 1;
+
+// What is making progress. The payload is `{ "version": 1, "kind": "download", ... }` with the
+// fields of the variant's struct next to `kind`, so a listener switches on `kind`.
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum ProgressEvent {
+    Download(DownloadProgress),
+    Extract(ExtractProgress),
+    Shell(ShellProgress),
+    Daemon(DaemonProgress),
+}
+
+#[derive(Clone, Serialize)]
+struct ProgressPayload {
+    version: u32,
+    #[serde(flatten)]
+    event: ProgressEvent,
+}
+
+// Sends `event` to every window as a `progress` event. A window that is gone just misses it.
+pub(crate) fn emit_progress(app: &tauri::AppHandle, event: ProgressEvent) {
+    let _ = app.emit_all(
+        PROGRESS_EVENT,
+        ProgressPayload {
+            version: PROGRESS_VERSION,
+            event,
+        },
+    );
+}
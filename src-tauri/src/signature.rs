@@ -0,0 +1,103 @@
+use crate::download::{checked_destination, http_client};
+use crate::error::AppError;
+use minisign_verify::{PublicKey, Signature};
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+
+// Minisign public key that signs bambooclaw releases (the base64 line of its minisign.pub).
+// It is pinned at build time through BAMBOOCLAW_RELEASE_PUBKEY rather than accepted from the
+// frontend, so a compromised download source can't bring its own key. A build without it fails,
+// rather than shipping an app that can't check any signature.
+const RELEASE_PUBLIC_KEY: &str = env!(
+    "BAMBOOCLAW_RELEASE_PUBKEY",
+    "set BAMBOOCLAW_RELEASE_PUBKEY to the base64 line of the release minisign.pub to build the app"
+);
+const SIGNATURE_FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+// 32. Check `file_path` against a detached minisign signature made with the release key.
+// `signature` is the path or http(s) URL of the .minisig file. `file_path` must lie where
+// download_binary may write (see checked_destination). A file whose contents don't match the
+// signature is deleted and SignatureInvalid returned. A malformed signature, or one made with
+// another key, is also SignatureInvalid but says nothing about the file, so it is kept, as it is
+// when the signature can't be fetched.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub async fn verify_signature(file_path: String, signature: String) -> Result<(), AppError> {
+    verify_file(&checked_destination(&file_path)?, &signature).await
+}
+
+// verify_signature for a path that is already known to be allowed, such as a download's `dest`
+pub(crate) async fn verify_file(file_path: &Path, signature: &str) -> Result<(), AppError> {
+    let public_key = PublicKey::from_base64(RELEASE_PUBLIC_KEY.trim())
+        .map_err(|e| AppError::Other(format!("The bundled release key is invalid: {}", e)))?;
+    let signature = Signature::decode(&load_signature(signature).await?)
+        .map_err(|e| AppError::SignatureInvalid(format!("Malformed signature: {}", e)))?;
+    let mut verifier = public_key.verify_stream(&signature).map_err(|e| {
+        AppError::SignatureInvalid(format!("Signature was not made by the release key: {}", e))
+    })?;
+
+    let mut file = tokio::fs::File::open(file_path)
+        .await
+        .map_err(|e| AppError::io(format!("Failed to open '{}'", file_path.display()), e))?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .await
+            .map_err(|e| AppError::io(format!("Failed to read '{}'", file_path.display()), e))?;
+        if n == 0 {
+            break;
+        }
+        verifier.update(&buf[..n]);
+    }
+    drop(file);
+    if let Err(e) = verifier.finalize() {
+        let _ = tokio::fs::remove_file(file_path).await;
+        return Err(AppError::SignatureInvalid(format!(
+            "'{}' does not match its signature and was deleted: {}",
+            file_path.display(),
+            e
+        )));
+    }
+    Ok(())
+}
+
+// The text of a .minisig file, read from disk or fetched
+async fn load_signature(location: &str) -> Result<String, AppError> {
+    if !location.starts_with("http://") && !location.starts_with("https://") {
+        return tokio::fs::read_to_string(location)
+            .await
+            .map_err(|e| AppError::io(format!("Failed to read signature '{}'", location), e));
+    }
+
+    let response = http_client(None)?
+        .get(location)
+        .timeout(SIGNATURE_FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| {
+            let message = format!("Failed to fetch signature '{}': {}", location, e);
+            if e.is_timeout() {
+                AppError::Timeout(message)
+            } else {
+                AppError::NetworkError(message)
+            }
+        })?;
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(AppError::NotFound(format!(
+            "No signature found at '{}'",
+            location
+        )));
+    }
+    if !status.is_success() {
+        return Err(AppError::NetworkError(format!(
+            "Fetching signature '{}' failed: HTTP {}",
+            location, status
+        )));
+    }
+    response.text().await.map_err(|e| {
+        AppError::NetworkError(format!("Failed to read signature '{}': {}", location, e))
+    })
+}
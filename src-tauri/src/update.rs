@@ -0,0 +1,522 @@
+use crate::daemon::daemon_binary_path;
+use crate::download::{check_executable, http_client, request_headers, set_executable};
+use crate::error::AppError;
+use crate::prerequisites::{lenient_version, parse_version};
+use crate::{get_arch, get_platform, run_checked, ShellOptions};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use semver::Version;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const RELEASES_URL: &str =
+    "https://api.github.com/repos/Enigmara-Technologies/bambooclaw-core/releases";
+const UPDATE_CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+// How long the latest release is reused, so a wizard session doesn't spend the hourly API quota
+const LATEST_RELEASE_TTL: Duration = Duration::from_secs(5 * 60);
+// Stops a misbehaving `Link` header from paging forever; 100 assets per page
+const MAX_ASSET_PAGES: usize = 10;
+// How many of the most recent releases are searched for the latest one of a channel; GitHub's
+// largest page
+const RECENT_RELEASES: usize = 100;
+
+// The latest release of each channel and when it was fetched
+static LATEST_RELEASE: Mutex<Vec<(Channel, Instant, Release)>> = Mutex::new(Vec::new());
+
+// The bambooclaw release this build of the app is made for; bump it along with the release
+const EXPECTED_DAEMON_VERSION: &str = "0.1.0";
+
+// Names an asset may use for each architecture, keyed by get_arch
+const ARCH_ALIASES: &[(&str, &[&str])] = &[
+    ("x64", &["x86_64", "x64", "amd64"]),
+    ("arm64", &["aarch64", "arm64"]),
+];
+
+// Endings of release assets that aren't the binary itself: signatures and checksums published
+// next to it, and archives and installers that would fail download_binary's `executable` check
+const NOT_BINARY_SUFFIXES: &[&str] = &[
+    ".minisig",
+    ".sig",
+    ".asc",
+    ".sha256",
+    ".sha256sum",
+    ".sha512",
+    ".md5",
+    ".txt",
+    ".json",
+    ".zip",
+    ".tar",
+    ".gz",
+    ".tgz",
+    ".xz",
+    ".bz2",
+    ".dmg",
+    ".msi",
+    ".pkg",
+    ".deb",
+    ".rpm",
+];
+
+// The parts of GitHub's release object we use
+#[derive(Clone, Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    assets: Vec<ReleaseAsset>,
+    // The paginated list of every asset, for releases with more than `assets` holds
+    #[serde(default)]
+    assets_url: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+    // ISO 8601, so it sorts as text; drafts have none
+    #[serde(default)]
+    published_at: Option<String>,
+}
+
+// Which releases count as the latest one. Stable only takes full releases; Prerelease also takes
+// betas and release candidates, whichever was published last. Drafts are never used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    #[default]
+    Stable,
+    Prerelease,
+}
+
+#[derive(Clone, Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+// Reply of check_for_update. `asset_url` is the latest release's binary for this OS and
+// architecture, or None when the release doesn't ship one.
+#[derive(Serialize)]
+pub struct UpdateInfo {
+    update_available: bool,
+    latest_version: String,
+    release_notes: String,
+    asset_url: Option<String>,
+}
+
+// Reply of check_bambooclaw_version. `warning` explains a mismatch and is None when the versions
+// agree.
+#[derive(Serialize)]
+pub struct VersionCheck {
+    installed_version: String,
+    expected_version: String,
+    matches: bool,
+    warning: Option<String>,
+}
+
+// 24. Check GitHub for a newer bambooclaw release than `current_version` (e.g. "0.3.1" or "v0.3.1").
+// `headers` are added to the API request, e.g. an `Authorization` token for a private repository
+// or a higher rate limit; they are never logged. Without one, a GITHUB_TOKEN environment
+// variable is used. The latest release is cached for 5 minutes.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(headers), err)]
+pub async fn check_for_update(
+    current_version: String,
+    headers: Option<HashMap<String, String>>,
+) -> Result<UpdateInfo, AppError> {
+    let headers = github_headers(request_headers(headers)?);
+    let current = parse_release_version(&current_version).ok_or_else(|| {
+        AppError::InvalidInput(format!("Invalid current version '{}'", current_version))
+    })?;
+    let release = fetch_release(None, Channel::Stable, &headers).await?;
+    let latest = parse_release_version(&release.tag_name).ok_or_else(|| {
+        AppError::Other(format!(
+            "Latest release has an unrecognised version tag '{}'",
+            release.tag_name
+        ))
+    })?;
+
+    let asset_url = find_platform_asset(&release, &headers)
+        .await?
+        .map(|a| a.browser_download_url);
+    Ok(UpdateInfo {
+        update_available: latest > current,
+        latest_version: latest.to_string(),
+        release_notes: release.body.unwrap_or_default(),
+        asset_url,
+    })
+}
+
+// 25. Find the download URL of the bambooclaw binary for this OS and architecture. `version`
+// (e.g. "0.3.1") picks the release tagged `v0.3.1`, prerelease or not; None or "latest" means
+// the newest release of `channel`: "stable" (the default) skips prereleases even when they are
+// newer, "prerelease" doesn't. `headers` are as in check_for_update.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(headers), err)]
+pub async fn resolve_binary_url(
+    version: Option<String>,
+    channel: Option<Channel>,
+    headers: Option<HashMap<String, String>>,
+) -> Result<String, AppError> {
+    let headers = github_headers(request_headers(headers)?);
+    let version = version
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty() && !v.eq_ignore_ascii_case("latest"));
+    let release = fetch_release(version, channel.unwrap_or_default(), &headers).await?;
+    find_platform_asset(&release, &headers)
+        .await?
+        .map(|a| a.browser_download_url)
+        .ok_or_else(|| {
+            AppError::NotFound(format!(
+                "Release {} has no binary for {}-{}",
+                release.tag_name,
+                get_platform(),
+                get_arch()
+            ))
+        })
+}
+
+// `headers` plus a bearer token from GITHUB_TOKEN, unless they already authenticate. A token
+// raises GitHub's limit from 60 to 5000 requests an hour.
+fn github_headers(mut headers: HeaderMap) -> HeaderMap {
+    let token = std::env::var("GITHUB_TOKEN").unwrap_or_default();
+    let token = token.trim();
+    if headers.contains_key(AUTHORIZATION) || token.is_empty() {
+        return headers;
+    }
+    if let Ok(mut value) = HeaderValue::from_str(&format!("Bearer {}", token)) {
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+    headers
+}
+
+// The release tagged with `version`, or the latest one of `channel`
+async fn fetch_release(
+    version: Option<&str>,
+    channel: Channel,
+    headers: &HeaderMap,
+) -> Result<Release, AppError> {
+    let Some(version) = version else {
+        return latest_release(channel, headers).await;
+    };
+
+    let url = format!(
+        "{}/tags/v{}",
+        RELEASES_URL,
+        version.strip_prefix('v').unwrap_or(version)
+    );
+    let response = github_get(&url, headers).await?;
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(AppError::NotFound(format!(
+            "No bambooclaw release found for version '{}'",
+            version
+        )));
+    }
+    if !status.is_success() {
+        return Err(AppError::NetworkError(format!(
+            "GitHub returned HTTP {} for {}",
+            status, url
+        )));
+    }
+    read_json(response).await
+}
+
+// The most recently published release of `channel`, from the RECENT_RELEASES newest ones. The
+// list is used rather than GitHub's `latest` endpoint, which only knows full releases.
+async fn latest_release(channel: Channel, headers: &HeaderMap) -> Result<Release, AppError> {
+    let cached = LATEST_RELEASE
+        .lock()
+        .unwrap()
+        .iter()
+        .find_map(|(c, fetched, release)| {
+            (*c == channel && fetched.elapsed() < LATEST_RELEASE_TTL).then(|| release.clone())
+        });
+    if let Some(release) = cached {
+        return Ok(release);
+    }
+
+    let url = format!("{}?per_page={}", RELEASES_URL, RECENT_RELEASES);
+    let response = github_get(&url, headers).await?;
+    if !response.status().is_success() {
+        return Err(AppError::NetworkError(format!(
+            "GitHub returned HTTP {} for {}",
+            response.status(),
+            url
+        )));
+    }
+    let release = read_json::<Vec<Release>>(response)
+        .await?
+        .into_iter()
+        .filter(|release| !release.draft && release.published_at.is_some())
+        .filter(|release| channel == Channel::Prerelease || !release.prerelease)
+        .max_by(|a, b| a.published_at.cmp(&b.published_at))
+        .ok_or_else(|| {
+            AppError::NotFound(
+                match channel {
+                    Channel::Stable => "No stable bambooclaw release has been published yet",
+                    Channel::Prerelease => "No bambooclaw release has been published yet",
+                }
+                .to_string(),
+            )
+        })?;
+
+    let mut cache = LATEST_RELEASE.lock().unwrap();
+    cache.retain(|(c, _, _)| *c != channel);
+    cache.push((channel, Instant::now(), release.clone()));
+    Ok(release)
+}
+
+// This platform's asset of `release`. When the assets listed with the release don't have one,
+// the full asset list is paged through, as the release object may not list them all.
+async fn find_platform_asset(
+    release: &Release,
+    headers: &HeaderMap,
+) -> Result<Option<ReleaseAsset>, AppError> {
+    if let Some(asset) = platform_asset(&release.assets) {
+        return Ok(Some(asset.clone()));
+    }
+    let Some(assets_url) = &release.assets_url else {
+        return Ok(None);
+    };
+
+    let mut assets: Vec<ReleaseAsset> = Vec::new();
+    let mut next = Some(format!("{}?per_page=100", assets_url));
+    for _ in 0..MAX_ASSET_PAGES {
+        let Some(url) = next.take() else { break };
+        let response = github_get(&url, headers).await?;
+        if !response.status().is_success() {
+            return Err(AppError::NetworkError(format!(
+                "GitHub returned HTTP {} for {}",
+                response.status(),
+                url
+            )));
+        }
+        next = next_page(response.headers());
+        assets.extend(read_json::<Vec<ReleaseAsset>>(response).await?);
+    }
+    Ok(platform_asset(&assets).cloned())
+}
+
+// GETs `url` from the GitHub API. An exhausted rate limit is RateLimited; any other status is
+// left to the caller.
+async fn github_get(url: &str, headers: &HeaderMap) -> Result<reqwest::Response, AppError> {
+    let response = http_client(None)?
+        .get(url)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .headers(headers.clone())
+        .timeout(UPDATE_CHECK_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| {
+            let message = format!("Failed to reach GitHub: {}", e);
+            if e.is_timeout() {
+                AppError::Timeout(message)
+            } else {
+                AppError::NetworkError(message)
+            }
+        })?;
+    if let Some(message) = rate_limit_error(&response) {
+        return Err(AppError::RateLimited(message));
+    }
+    Ok(response)
+}
+
+async fn read_json<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, AppError> {
+    let body = response
+        .text()
+        .await
+        .map_err(|e| AppError::NetworkError(format!("Failed to read GitHub's response: {}", e)))?;
+    serde_json::from_str(&body)
+        .map_err(|e| AppError::Other(format!("Unexpected response from GitHub: {}", e)))
+}
+
+// The `rel="next"` URL of a `Link` header, as in
+// `<https://api.github.com/...?page=2>; rel="next", <...?page=5>; rel="last"`
+fn next_page(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#)
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+// GitHub answers 403 (or 429) with `x-ratelimit-remaining: 0` once the hourly unauthenticated
+// quota is spent; `x-ratelimit-reset` is when it refills, in Unix seconds
+fn rate_limit_error(response: &reqwest::Response) -> Option<String> {
+    let status = response.status();
+    if status != reqwest::StatusCode::FORBIDDEN && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+    {
+        return None;
+    }
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+    };
+    if header("x-ratelimit-remaining") != Some(0)
+        && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+    {
+        return None;
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Some(match header("x-ratelimit-reset") {
+        Some(reset) if reset > now => format!(
+            "GitHub API rate limit reached; try again in {} minute(s)",
+            (reset - now).div_ceil(60)
+        ),
+        _ => "GitHub API rate limit reached; try again later".to_string(),
+    })
+}
+
+// 43. The version of the installed bambooclaw binary, from `bambooclaw --version`. NotFound when
+// it hasn't been downloaded yet.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub async fn get_bambooclaw_version() -> Result<String, AppError> {
+    let binary = daemon_binary_path()?;
+    if !binary.is_file() {
+        return Err(AppError::NotFound(format!(
+            "bambooclaw is not installed at {}",
+            binary.display()
+        )));
+    }
+    binary_version(&binary).await
+}
+
+// 48. Check that a downloaded binary actually runs here, the last step of an install: it is made
+// executable and run with `--version`, which has to succeed and print a version, which is
+// returned. A file for another OS or architecture, a corrupt one, or one that fails to run is
+// NotExecutable.
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub async fn validate_binary(path: String) -> Result<String, AppError> {
+    let path = Path::new(&path);
+    if !path.is_file() {
+        return Err(AppError::NotFound(format!("No file at {}", path.display())));
+    }
+    check_executable(path)?;
+    set_executable(path)?;
+    binary_version(path).await.map_err(|e| match e {
+        AppError::Timeout(_) => e,
+        e => AppError::NotExecutable(format!(
+            "The downloaded binary is not runnable on this system: {}",
+            e
+        )),
+    })
+}
+
+// `<binary> --version`, parsed
+async fn binary_version(binary: &Path) -> Result<String, AppError> {
+    let output = run_checked(
+        &binary.to_string_lossy(),
+        &["--version".to_string()],
+        &ShellOptions::with_timeout(VERSION_TIMEOUT),
+    )
+    .await?;
+    parse_version(&output)
+        .map(|version| version.to_string())
+        .ok_or_else(|| {
+            AppError::Other(format!(
+                "Unrecognised output from {} --version: {}",
+                binary.display(),
+                output.trim()
+            ))
+        })
+}
+
+// 44. Compare the installed binary with EXPECTED_DAEMON_VERSION, for the startup check. A
+// mismatch, typically an app update without a new binary, is reported as a warning in the reply
+// rather than an error, so the UI can offer to download the matching release.
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub async fn check_bambooclaw_version() -> Result<VersionCheck, AppError> {
+    let installed_version = get_bambooclaw_version().await?;
+    let matches = installed_version == EXPECTED_DAEMON_VERSION;
+    let warning = (!matches).then(|| {
+        format!(
+            "Installed bambooclaw is {} but this app expects {}; download the matching release",
+            installed_version, EXPECTED_DAEMON_VERSION
+        )
+    });
+    if let Some(warning) = &warning {
+        tracing::warn!("{}", warning);
+    }
+    Ok(VersionCheck {
+        installed_version,
+        expected_version: EXPECTED_DAEMON_VERSION.to_string(),
+        matches,
+        warning,
+    })
+}
+
+// Release tags look like `v1.2.3` or `v1.2.3-beta.1`; short tags such as `v1.2` are padded
+fn parse_release_version(tag: &str) -> Option<Version> {
+    let tag = tag.trim();
+    let tag = tag.strip_prefix('v').unwrap_or(tag);
+    Version::parse(tag).ok().or_else(|| lenient_version(tag))
+}
+
+// The release version in a download URL, from a path segment such as `v0.3.1` or `0.3.1`, as in
+// GitHub's .../releases/download/v0.3.1/bambooclaw-linux-x64
+pub(crate) fn version_in_url(url: &str) -> Option<String> {
+    url.split(['/', '?', '#'])
+        .filter(|segment| {
+            let digits = segment.strip_prefix('v').unwrap_or(segment);
+            digits.starts_with(|c: char| c.is_ascii_digit()) && digits.contains('.')
+        })
+        .find_map(parse_release_version)
+        .map(|version| version.to_string())
+}
+
+// Picks the binary for this OS, preferring one named for this architecture over one that names
+// none. Signatures, checksums, archives and installers published next to it are skipped: the
+// asset is downloaded as the executable itself.
+fn platform_asset(assets: &[ReleaseAsset]) -> Option<&ReleaseAsset> {
+    let os_markers: &[&str] = match get_platform().as_str() {
+        "windows" => &[".exe", "windows"],
+        "macos" => &["macos", "darwin"],
+        _ => &[".appimage", "linux"],
+    };
+    let for_os: Vec<&ReleaseAsset> = assets
+        .iter()
+        .filter(|a| {
+            let name = a.name.to_lowercase();
+            os_markers.iter().any(|m| name.contains(m))
+                && !NOT_BINARY_SUFFIXES.iter().any(|s| name.ends_with(s))
+        })
+        .collect();
+
+    let arch_of = |asset: &ReleaseAsset| {
+        let name = asset.name.to_lowercase();
+        ARCH_ALIASES
+            .iter()
+            .find(|(_, aliases)| aliases.iter().any(|a| name.contains(a)))
+            .map(|(arch, _)| *arch)
+    };
+    let arch = get_arch();
+    for_os
+        .iter()
+        .find(|a| arch_of(a) == Some(arch.as_str()))
+        .or_else(|| for_os.iter().find(|a| arch_of(a).is_none()))
+        .copied()
+}
@@ -0,0 +1,313 @@
+use crate::bambooclaw_dir;
+use crate::config::{config_diff, get_config_path, remove_config};
+use crate::daemon::{self, daemon_binary_path, DaemonState, DEFAULT_GRACEFUL_TIMEOUT};
+use crate::download::plan_download;
+use crate::error::AppError;
+use crate::install::{plan_path_update, remove_from_user_path};
+use crate::update::resolve_binary_url;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// Serializes updates to install_state.json, which several commands may make at once
+static STATE_LOCK: Mutex<()> = Mutex::new(());
+
+// How far the boot wizard has got, kept in install_state.json in the bambooclaw dir so quitting
+// midway resumes at the same step. Each flag is set by the command that completes that milestone;
+// `prereqs_installed` and `completed` are set by the wizard itself through set_install_step.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstallState {
+    prereqs_checked: bool,
+    prereqs_installed: bool,
+    binary_downloaded: bool,
+    path_added: bool,
+    config_written: bool,
+    completed: bool,
+    // Where the current binary was downloaded from, for reinstall
+    last_install: Option<InstallInfo>,
+}
+
+// Recorded whenever download_binary writes the daemon binary. `url` is the URL or mirror that
+// served it; `version` is read from a release URL such as .../download/v0.3.1/..., so it is None
+// for other URLs. `timestamp` is in Unix seconds.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InstallInfo {
+    pub(crate) url: String,
+    pub(crate) version: Option<String>,
+    pub(crate) sha256: String,
+    pub(crate) timestamp: u64,
+}
+
+impl InstallState {
+    fn step_mut(&mut self, step: &str) -> Option<&mut bool> {
+        Some(match step {
+            "prereqs_checked" => &mut self.prereqs_checked,
+            "prereqs_installed" => &mut self.prereqs_installed,
+            "binary_downloaded" => &mut self.binary_downloaded,
+            "path_added" => &mut self.path_added,
+            "config_written" => &mut self.config_written,
+            "completed" => &mut self.completed,
+            _ => return None,
+        })
+    }
+}
+
+// Reply of rollback_install: one line per step undone, and per step that couldn't be
+#[derive(Serialize)]
+pub struct RollbackReport {
+    rolled_back: Vec<String>,
+    failed: Vec<String>,
+}
+
+// One step of plan_install, named like the InstallState flag it sets. `changes` is false when
+// the step has nothing to do.
+#[derive(Serialize)]
+pub struct PlannedStep {
+    step: &'static str,
+    changes: bool,
+    description: String,
+}
+
+// Reply of plan_install
+#[derive(Serialize)]
+pub struct InstallPlan {
+    steps: Vec<PlannedStep>,
+}
+
+// 40. The wizard's saved progress; every step is false on first run
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub fn get_install_state() -> Result<InstallState, AppError> {
+    load_state()
+}
+
+// 55. Where the installed binary came from, or None if it wasn't downloaded by this app
+#[tauri::command]
+#[tracing::instrument(target = "command", level = "debug", err)]
+pub fn get_last_install_info() -> Result<Option<InstallInfo>, AppError> {
+    last_install()
+}
+
+// 41. Mark a wizard step as done or not done and return the updated progress. `step` is one of
+// the InstallState fields, e.g. "prereqs_installed".
+#[tauri::command]
+#[tracing::instrument(target = "command", err)]
+pub fn set_install_step(step: String, done: bool) -> Result<InstallState, AppError> {
+    let _guard = STATE_LOCK.lock().unwrap();
+    let mut state = load_state()?;
+    *state
+        .step_mut(&step)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown install step '{}'", step)))? = done;
+    save_state(&state)?;
+    Ok(state)
+}
+
+// 42. Undo what a failed or abandoned installation did, newest step first, so the wizard can
+// start over: config.toml is removed (a copy stays in backups), the PATH change is reverted, and
+// the downloaded binary is deleted after stopping any daemon running from it. Tools installed by
+// package managers during the prerequisite step are left in place, as is the bambooclaw dir,
+// which holds the app's own logs. Steps that couldn't be undone stay marked as done, so a later
+// rollback tries them again; the rest are reset.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(app, daemon_state), err)]
+pub async fn rollback_install(
+    app: tauri::AppHandle,
+    daemon_state: tauri::State<'_, DaemonState>,
+) -> Result<RollbackReport, AppError> {
+    let progress = load_state()?;
+    // Kept, so what was installed can still be reinstalled after starting over
+    let mut remaining = InstallState {
+        last_install: progress.last_install.clone(),
+        ..InstallState::default()
+    };
+    let mut report = RollbackReport {
+        rolled_back: Vec::new(),
+        failed: Vec::new(),
+    };
+
+    if progress.config_written {
+        match remove_config() {
+            Ok(Some(path)) => report.rolled_back.push(format!(
+                "Removed {} (a copy was kept in backups)",
+                path.display()
+            )),
+            Ok(None) => {}
+            Err(e) => {
+                report.failed.push(e.to_string());
+                remaining.config_written = true;
+            }
+        }
+    }
+
+    if progress.path_added {
+        let dir = bambooclaw_dir()?;
+        match remove_from_user_path(&dir).await {
+            Ok(true) => report
+                .rolled_back
+                .push(format!("Removed {} from PATH", dir.display())),
+            Ok(false) => {}
+            Err(e) => {
+                report.failed.push(e.to_string());
+                remaining.path_added = true;
+            }
+        }
+    }
+
+    if progress.binary_downloaded {
+        if daemon::current_daemon_pid(&daemon_state).is_some() {
+            daemon::shutdown_any_daemon(&app, &daemon_state, DEFAULT_GRACEFUL_TIMEOUT).await;
+            daemon::remove_pid_file();
+            report.rolled_back.push("Stopped the daemon".to_string());
+        }
+        let binary = daemon_binary_path()?;
+        match std::fs::remove_file(&binary) {
+            Ok(()) => report
+                .rolled_back
+                .push(format!("Removed {}", binary.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                report.failed.push(
+                    AppError::io(format!("Failed to remove {}", binary.display()), e).to_string(),
+                );
+                remaining.binary_downloaded = true;
+            }
+        }
+    }
+
+    let _guard = STATE_LOCK.lock().unwrap();
+    save_state(&remaining)?;
+    Ok(report)
+}
+
+// 61. What installing would do, without doing any of it, for showing before the user commits:
+// the binary download with its size and destination, the PATH change, and the config.toml diff.
+// These are the dry runs of download_binary, add_to_path and write_config. `url` defaults to the
+// latest stable release for this platform; `config` is the config.toml the wizard would save,
+// and without it the plan only says whether the default config would be written.
+#[tauri::command]
+#[tracing::instrument(target = "command", skip(daemon_state, config), err)]
+pub async fn plan_install(
+    daemon_state: tauri::State<'_, DaemonState>,
+    url: Option<String>,
+    config: Option<String>,
+) -> Result<InstallPlan, AppError> {
+    let mut steps = Vec::new();
+
+    let url = match url {
+        Some(url) => url,
+        None => resolve_binary_url(None, None, None).await?,
+    };
+    let binary = daemon_binary_path()?;
+    let download = plan_download(&url, &binary.to_string_lossy(), None, &HeaderMap::new()).await?;
+    let mut description = download.describe();
+    if binary.exists() {
+        description.push_str(", replacing the binary already there");
+    }
+    if let Some(pid) = daemon::current_daemon_pid(&daemon_state) {
+        description.push_str(&format!(
+            "; the daemon (pid {}) is running from it and has to be stopped first",
+            pid
+        ));
+    }
+    steps.push(PlannedStep {
+        step: "binary_downloaded",
+        changes: true,
+        description,
+    });
+
+    let dir = bambooclaw_dir()?;
+    let path = plan_path_update().await?;
+    steps.push(PlannedStep {
+        step: "path_added",
+        changes: path.added,
+        description: if path.added {
+            format!("Add {} to PATH in {}", dir.display(), path.location)
+        } else {
+            format!("{} is already on PATH", dir.display())
+        },
+    });
+
+    let config_path = get_config_path(None)?;
+    let (changes, description) = match config {
+        Some(content) => {
+            let diff = config_diff(&content, None)?;
+            if diff.is_empty() {
+                (false, format!("{} already has these settings", config_path))
+            } else {
+                (
+                    true,
+                    format!("Change {}:\n{}", config_path, diff.join("\n")),
+                )
+            }
+        }
+        None if std::path::Path::new(&config_path).exists() => {
+            (false, format!("Keep the existing {}", config_path))
+        }
+        None => (true, format!("Write the default config to {}", config_path)),
+    };
+    steps.push(PlannedStep {
+        step: "config_written",
+        changes,
+        description,
+    });
+
+    Ok(InstallPlan { steps })
+}
+
+// Records a milestone reached by another command. Progress is only a convenience, so failing
+// to save it never fails that command.
+pub(crate) fn complete_step(step: &str) {
+    if let Err(e) = set_install_step(step.to_string(), true) {
+        tracing::warn!(step, "failed to save install progress: {}", e);
+    }
+}
+
+// Like complete_step, failing to save never fails the download
+pub(crate) fn record_install(info: InstallInfo) {
+    let _guard = STATE_LOCK.lock().unwrap();
+    let result = load_state().and_then(|mut state| {
+        state.last_install = Some(info);
+        save_state(&state)
+    });
+    if let Err(e) = result {
+        tracing::warn!("failed to record the install: {}", e);
+    }
+}
+
+pub(crate) fn last_install() -> Result<Option<InstallInfo>, AppError> {
+    Ok(load_state()?.last_install)
+}
+
+fn state_path() -> Result<PathBuf, AppError> {
+    Ok(bambooclaw_dir()?.join("install_state.json"))
+}
+
+// A missing or unreadable file is treated as a fresh install
+fn load_state() -> Result<InstallState, AppError> {
+    let content = match std::fs::read_to_string(state_path()?) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(InstallState::default()),
+        Err(e) => return Err(AppError::io("Failed to read install_state.json", e)),
+    };
+    Ok(serde_json::from_str(&content).unwrap_or_default())
+}
+
+// Written to a temp file and renamed, so a crash can't leave a half-written file
+fn save_state(state: &InstallState) -> Result<(), AppError> {
+    let path = state_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::io(format!("Failed to create {}", dir.display()), e))?;
+    }
+    let content = serde_json::to_string_pretty(state)
+        .map_err(|e| AppError::Other(format!("Failed to serialize install state: {}", e)))?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, content)
+        .and_then(|_| std::fs::rename(&tmp, &path))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&tmp);
+            AppError::io("Failed to write install_state.json", e)
+        })
+}
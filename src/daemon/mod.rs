@@ -9,25 +9,58 @@ use tokio::time::Duration;
 const STATUS_FLUSH_SECONDS: u64 = 5;
 
 pub async fn run(config: Config, host: String, port: u16) -> Result<()> {
+    crate::health::mark_component_ok("daemon");
+    let state_writer = spawn_state_writer(config.clone());
+    let mut reload = ReloadSignal::new()?;
+
+    println!("🧠 BambooClawCore daemon started");
+    println!("   Gateway:  http://{host}:{port}");
+    println!("   Components: gateway, channels, heartbeat, scheduler");
+    println!("   Ctrl+C to stop");
+
+    // A reload (SIGHUP on Unix) re-reads config.toml and restarts the components with it; the
+    // process, its PID and the gateway address stay the same
+    let mut config = config;
+    loop {
+        let handles = spawn_components(&config, &host, port).await;
+        let reload_requested = tokio::select! {
+            result = tokio::signal::ctrl_c() => {
+                result?;
+                false
+            }
+            () = reload.recv() => true,
+        };
+        stop_components(handles).await;
+        if !reload_requested {
+            break;
+        }
+        config = reload_config(config).await;
+    }
+
+    crate::health::mark_component_error("daemon", "shutdown requested");
+    state_writer.abort();
+    let _ = state_writer.await;
+    Ok(())
+}
+
+async fn spawn_components(config: &Config, host: &str, port: u16) -> Vec<JoinHandle<()>> {
     let initial_backoff = config.reliability.channel_initial_backoff_secs.max(1);
     let max_backoff = config
         .reliability
         .channel_max_backoff_secs
         .max(initial_backoff);
 
-    crate::health::mark_component_ok("daemon");
-
     if config.heartbeat.enabled {
         let _ =
             crate::heartbeat::engine::HeartbeatEngine::ensure_heartbeat_file(&config.workspace_dir)
                 .await;
     }
 
-    let mut handles: Vec<JoinHandle<()>> = vec![spawn_state_writer(config.clone())];
+    let mut handles: Vec<JoinHandle<()>> = Vec::new();
 
     {
         let gateway_cfg = config.clone();
-        let gateway_host = host.clone();
+        let gateway_host = host.to_string();
         handles.push(spawn_component_supervisor(
             "gateway",
             initial_backoff,
@@ -41,7 +74,7 @@ pub async fn run(config: Config, host: String, port: u16) -> Result<()> {
     }
 
     {
-        if has_supervised_channels(&config) {
+        if has_supervised_channels(config) {
             let channels_cfg = config.clone();
             handles.push(spawn_component_supervisor(
                 "channels",
@@ -87,22 +120,59 @@ pub async fn run(config: Config, host: String, port: u16) -> Result<()> {
         tracing::info!("Cron disabled; scheduler supervisor not started");
     }
 
-    println!("🧠 BambooClawCore daemon started");
-    println!("   Gateway:  http://{host}:{port}");
-    println!("   Components: gateway, channels, heartbeat, scheduler");
-    println!("   Ctrl+C to stop");
-
-    tokio::signal::ctrl_c().await?;
-    crate::health::mark_component_error("daemon", "shutdown requested");
+    handles
+}
 
+async fn stop_components(handles: Vec<JoinHandle<()>>) {
     for handle in &handles {
         handle.abort();
     }
     for handle in handles {
         let _ = handle.await;
     }
+}
 
-    Ok(())
+/// Loads config.toml again. A config that fails to load leaves the current one in effect; either
+/// way the outcome is recorded as the `config` health component, whose `last_ok` tells clients
+/// when the last successful reload happened.
+async fn reload_config(current: Config) -> Config {
+    match Config::load_or_init().await {
+        Ok(mut config) => {
+            config.apply_env_overrides();
+            crate::health::mark_component_ok("config");
+            tracing::info!("Reloaded config from {}", config.config_path.display());
+            config
+        }
+        Err(e) => {
+            crate::health::mark_component_error("config", format!("reload failed: {e}"));
+            tracing::error!("Config reload failed, keeping the previous config: {e}");
+            current
+        }
+    }
+}
+
+/// Requests to reload the config: SIGHUP on Unix. Windows has no equivalent, so there a
+/// reload never arrives and the daemon has to be restarted instead.
+struct ReloadSignal {
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+}
+
+impl ReloadSignal {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            hangup: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?,
+        })
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if self.hangup.recv().await.is_some() {
+            return;
+        }
+        std::future::pending::<()>().await;
+    }
 }
 
 pub fn state_file_path(config: &Config) -> PathBuf {